@@ -0,0 +1,95 @@
+use crate::Error;
+use poise::serenity_prelude as serenity;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+/// A record of someone accessing members' data in bulk.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AuditEntry {
+    pub at: serenity::Timestamp,
+    pub guild_id: Option<serenity::GuildId>,
+    pub user_id: serenity::UserId,
+    /// What was done, e.g. `export`.
+    pub action: String,
+    pub details: String,
+}
+
+/// An append-only log of audit entries, one JSON object per line. Entries are kept when users
+/// erase their data, as they are the servers' record of who accessed their members' data.
+pub struct AuditLog {
+    path: PathBuf,
+    // Keeps lines from interleaving when two entries are written at once
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn open(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Appends an entry for an action taken now.
+    pub fn record(
+        &self,
+        guild_id: Option<serenity::GuildId>,
+        user_id: serenity::UserId,
+        action: &str,
+        details: String,
+    ) -> Result<(), Error> {
+        let entry = AuditEntry {
+            at: serenity::Timestamp::now(),
+            guild_id,
+            user_id,
+            action: action.to_string(),
+            details,
+        };
+
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+
+        let _guard = self.lock.lock().unwrap();
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        file.write_all(&line)?;
+        file.sync_all()?;
+
+        Ok(())
+    }
+
+    /// Returns every entry for actions taken by a user.
+    pub fn by_user(&self, user_id: serenity::UserId) -> Result<Vec<AuditEntry>, Error> {
+        let _guard = self.lock.lock().unwrap();
+
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            let entry: AuditEntry = serde_json::from_str(line)?;
+
+            if entry.user_id == user_id {
+                entries.push(entry);
+            }
+        }
+
+        Ok(entries)
+    }
+}
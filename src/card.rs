@@ -0,0 +1,314 @@
+use crate::{
+    catalog::{TraitCatalog, TraitKind},
+    fursona::Fursona,
+    palette::Palette,
+    render, Error,
+};
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use image::{imageops, Rgba, RgbaImage};
+use std::{fs, io::Cursor, path::Path};
+
+const PADDING: u32 = 24;
+const TITLE_SIZE: f32 = 36.0;
+const TEXT_SIZE: f32 = 22.0;
+const SWATCH_SIZE: u32 = 96;
+/// The largest card that can be configured, to keep rendering cheap.
+const MAX_SIZE: u32 = 2000;
+
+/// Font used for the trait text unless the catalog sets its own.
+static DEFAULT_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+/// How fursona cards are laid out, configured in the `[card]` section of the trait catalog.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CardConfig {
+    pub width: u32,
+    pub height: u32,
+    /// Font used for the trait text, relative to the assets directory. The bundled DejaVu Sans
+    /// is used if this isn't set.
+    pub font: Option<String>,
+    /// The traits whose sprites are drawn, from the bottom layer to the top.
+    pub layers: Vec<TraitKind>,
+}
+
+impl Default for CardConfig {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 400,
+            font: None,
+            layers: vec![
+                TraitKind::Species,
+                TraitKind::BodyType,
+                TraitKind::Markings,
+                TraitKind::Accessories,
+            ],
+        }
+    }
+}
+
+impl CardConfig {
+    /// Explains what is wrong with the layout, if the card couldn't be drawn with it.
+    pub fn problem(&self) -> Option<String> {
+        // The portrait is a square filling the height inside the padding, with text beside it
+        if self.height <= PADDING * 2 || self.height > MAX_SIZE {
+            return Some(format!(
+                "card height must be between {} and {MAX_SIZE}",
+                PADDING * 2 + 1
+            ));
+        }
+
+        if self.width < self.height || self.width > MAX_SIZE {
+            return Some(format!(
+                "card width must be between the height ({}) and {MAX_SIZE}",
+                self.height
+            ));
+        }
+
+        None
+    }
+}
+
+/// Renders a character card for a fursona and returns it encoded as a PNG.
+///
+/// Sprites and the font are loaded from `assets_dir`. Options without a sprite are skipped, so
+/// a catalog without any art still produces a card with the trait text, drawn in the bundled
+/// font if the catalog doesn't set one.
+pub fn render_card(
+    fursona: &Fursona,
+    catalog: &TraitCatalog,
+    assets_dir: &Path,
+) -> Result<Vec<u8>, Error> {
+    let config = &catalog.card;
+    let (r, g, b) = render::colour(fursona).tuple();
+
+    let mut card = RgbaImage::from_pixel(config.width, config.height, Rgba([r, g, b, 255]));
+
+    // Character portrait, a square on the left built up from the trait sprites
+    let portrait_size = config.height - PADDING * 2;
+    let mut portrait = RgbaImage::from_pixel(portrait_size, portrait_size, Rgba([255; 4]));
+
+    for &kind in &config.layers {
+        for sprite in sprites(fursona, catalog, kind) {
+            let path = assets_dir.join(sprite);
+
+            let layer = match image::open(&path) {
+                Ok(layer) => layer,
+                Err(e) => {
+                    println!("Skipping card layer `{}`: {}", path.display(), e);
+                    continue;
+                }
+            };
+
+            let layer = layer
+                .resize_exact(portrait_size, portrait_size, imageops::FilterType::Triangle)
+                .to_rgba8();
+
+            imageops::overlay(&mut portrait, &layer, 0, 0);
+        }
+    }
+
+    imageops::overlay(&mut card, &portrait, PADDING as i64, PADDING as i64);
+
+    // Trait text on the right
+    let font = match &config.font {
+        Some(font) => FontVec::try_from_vec(fs::read(assets_dir.join(font))?)?,
+        None => FontVec::try_from_vec(DEFAULT_FONT.to_vec())?,
+    };
+
+    // Light text on dark backgrounds, so it stays readable on any palette
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    let text_colour = if luminance < 140.0 { [255; 3] } else { [0; 3] };
+
+    let x = (portrait_size + PADDING * 2) as f32;
+    let mut y = PADDING as f32;
+
+    draw_text(
+        &mut card,
+        &font,
+        TITLE_SIZE,
+        x,
+        y,
+        text_colour,
+        &fursona.name,
+    );
+    y += TITLE_SIZE * 1.5;
+
+    for kind in TraitKind::ALL {
+        let line = format!(
+            "{}: {}",
+            kind.name(),
+            render::trait_value(fursona, catalog, kind)
+        );
+
+        draw_text(&mut card, &font, TEXT_SIZE, x, y, text_colour, &line);
+        y += TEXT_SIZE * 1.4;
+    }
+
+    let mut png = Vec::new();
+    card.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+/// Renders a fursona's palette as a row of colour swatches and returns it encoded as a PNG.
+pub fn render_swatches(palette: &Palette) -> Result<Vec<u8>, Error> {
+    let colours = palette.colours();
+    let width = (SWATCH_SIZE + PADDING) * colours.len() as u32 + PADDING;
+    let height = SWATCH_SIZE + PADDING * 2;
+
+    let mut image = RgbaImage::new(width, height);
+
+    for (i, (_, colour)) in colours.iter().enumerate() {
+        let (r, g, b) = colour.tuple();
+        let swatch = RgbaImage::from_pixel(SWATCH_SIZE, SWATCH_SIZE, Rgba([r, g, b, 255]));
+        let x = PADDING + (SWATCH_SIZE + PADDING) * i as u32;
+
+        imageops::overlay(&mut image, &swatch, x as i64, PADDING as i64);
+    }
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+
+    Ok(png)
+}
+
+/// Returns the sprite paths for the options a fursona has picked for a trait.
+fn sprites<'a>(fursona: &'a Fursona, catalog: &'a TraitCatalog, kind: TraitKind) -> Vec<&'a str> {
+    let set = catalog.get(kind);
+
+    fursona
+        .values(kind)
+        .iter()
+        .filter_map(|value| set.find(value)?.sprite.as_deref())
+        .collect()
+}
+
+/// Draws a single line of text in an RGB colour with its top-left corner at `(x, y)`.
+fn draw_text(
+    image: &mut RgbaImage,
+    font: &FontVec,
+    size: f32,
+    x: f32,
+    y: f32,
+    colour: [u8; 3],
+    text: &str,
+) {
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+
+    let mut caret = x;
+
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        let glyph = id.with_scale_and_position(scale, ab_glyph::point(caret, y + scaled.ascent()));
+
+        caret += scaled.h_advance(id);
+
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+
+        let bounds = outlined.px_bounds();
+
+        outlined.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+
+            if px < 0 || py < 0 || px >= image.width() as i64 || py >= image.height() as i64 {
+                return;
+            }
+
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+
+            for (channel, target) in pixel.0[..3].iter_mut().zip(colour) {
+                *channel = (*channel as f32 * (1.0 - coverage) + target as f32 * coverage) as u8;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn catalog() -> TraitCatalog {
+        toml::from_str(include_str!("../traits.toml")).unwrap()
+    }
+
+    fn fursona(base: u32) -> Fursona {
+        let mut fursona = Fursona::new(
+            "Rusty".to_string(),
+            "fox".to_string(),
+            "slim".to_string(),
+            "stripes".to_string(),
+            vec!["scarf".to_string(), "tail".to_string()],
+            "shy".to_string(),
+        );
+        fursona.palette = Some(Palette::new(base, 0xF4EDE4, 0xE0A526));
+
+        fursona
+    }
+
+    fn render(fursona: &Fursona) -> RgbaImage {
+        // None of the bundled options have sprites, so nothing is read from the assets directory
+        let png = render_card(fursona, &catalog(), Path::new("missing-assets")).unwrap();
+
+        image::load_from_memory(&png).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn renders_without_sprites() {
+        let card = render(&fursona(0xC8553D));
+        let config = &catalog().card;
+
+        assert_eq!(card.dimensions(), (config.width, config.height));
+        assert_eq!(card.get_pixel(0, 0), &Rgba([0xC8, 0x55, 0x3D, 255]));
+        assert_eq!(card.get_pixel(PADDING, PADDING), &Rgba([255; 4]));
+    }
+
+    #[test]
+    fn text_contrasts_with_background() {
+        let text_pixels = |card: &RgbaImage, colour: [u8; 3]| {
+            card.enumerate_pixels()
+                .filter(|(x, ..)| *x > card.height())
+                .filter(|(.., p)| p.0[..3] == colour)
+                .count()
+        };
+
+        assert!(text_pixels(&render(&fursona(0x1B1B2F)), [255; 3]) > 0);
+        assert!(text_pixels(&render(&fursona(0xF4EDE4)), [0; 3]) > 0);
+    }
+
+    #[test]
+    fn matches_snapshot() {
+        let card = render(&fursona(0xC8553D));
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots/card.png");
+
+        // Run with UPDATE_SNAPSHOTS=1 after changing how cards look
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            card.save(&path).unwrap();
+        }
+
+        let snapshot = image::open(&path)
+            .expect("missing snapshot, run with UPDATE_SNAPSHOTS=1 to create it")
+            .to_rgba8();
+
+        assert!(snapshot == card, "card doesn't match {}", path.display());
+    }
+
+    #[test]
+    fn rejects_unusable_sizes() {
+        let config = |width, height| CardConfig {
+            width,
+            height,
+            ..CardConfig::default()
+        };
+
+        assert!(config(800, 400).problem().is_none());
+        assert!(config(800, PADDING * 2).problem().is_some());
+        assert!(config(300, 400).problem().is_some());
+        assert!(config(MAX_SIZE + 1, 400).problem().is_some());
+    }
+}
@@ -0,0 +1,706 @@
+use crate::{card::CardConfig, fursona::Fursona, render, store, wizard, Error};
+use poise::serenity_prelude as serenity;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Discord only allows this many options in a select menu.
+pub const MAX_OPTIONS: usize = 25;
+
+/// The most options a select menu can have picked at once.
+const MAX_PICKS: usize = MAX_OPTIONS;
+
+/// The categories of traits a fursona is made up of.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    poise::ChoiceParameter,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TraitKind {
+    Species,
+    #[name = "Body Type"]
+    BodyType,
+    Markings,
+    Accessories,
+    Personality,
+}
+
+impl TraitKind {
+    pub const ALL: [TraitKind; 5] = [
+        TraitKind::Species,
+        TraitKind::BodyType,
+        TraitKind::Markings,
+        TraitKind::Accessories,
+        TraitKind::Personality,
+    ];
+
+    /// The identifier used for this trait in custom ids and data files.
+    pub fn id(self) -> &'static str {
+        match self {
+            TraitKind::Species => "species",
+            TraitKind::BodyType => "body_type",
+            TraitKind::Markings => "markings",
+            TraitKind::Accessories => "accessories",
+            TraitKind::Personality => "personality",
+        }
+    }
+
+    /// The human readable name of this trait.
+    pub fn name(self) -> &'static str {
+        match self {
+            TraitKind::Species => "Species",
+            TraitKind::BodyType => "Body Type",
+            TraitKind::Markings => "Markings",
+            TraitKind::Accessories => "Accessories",
+            TraitKind::Personality => "Personality",
+        }
+    }
+
+    /// Whether more than one option of this trait can be picked.
+    pub fn multiple(self) -> bool {
+        matches!(self, TraitKind::Accessories)
+    }
+
+    /// How many options are picked for this trait when the catalog doesn't say.
+    pub fn default_picks(self) -> Picks {
+        match self {
+            TraitKind::Accessories => Picks {
+                min: 0,
+                max: 3,
+                weights: Vec::new(),
+            },
+            _ => Picks {
+                min: 1,
+                max: 1,
+                weights: Vec::new(),
+            },
+        }
+    }
+}
+
+/// How many options of a trait are picked, both in the wizard and when rolling.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Picks {
+    pub min: u8,
+    pub max: u8,
+    /// Relative chance of rolling each count from `min` to `max`, all equally likely if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weights: Vec<f64>,
+}
+
+impl Picks {
+    /// Returns why these picks can't be used for a trait, if they can't.
+    fn validate(&self, kind: TraitKind) -> Option<String> {
+        if self.min > self.max {
+            return Some(format!("{} picks have a min above their max", kind.id()));
+        }
+
+        if !kind.multiple() && (self.min, self.max) != (1, 1) {
+            return Some(format!("{} must pick exactly one option", kind.id()));
+        }
+
+        if self.max as usize > MAX_PICKS {
+            return Some(format!(
+                "{} can't pick more than {MAX_PICKS} options",
+                kind.id()
+            ));
+        }
+
+        let weights = self.weights.len();
+        let counts = (self.max - self.min) as usize + 1;
+
+        if weights != 0 && weights != counts {
+            return Some(format!(
+                "{} picks need {counts} weights, one for each count from min to max",
+                kind.id()
+            ));
+        }
+
+        if weights != 0
+            && (self.weights.iter().any(|w| !w.is_finite() || *w < 0.0)
+                || self.weights.iter().sum::<f64>() <= 0.0)
+        {
+            return Some(format!("{} pick weights must be positive", kind.id()));
+        }
+
+        None
+    }
+}
+
+/// How rare an option is when rolling random fursonas.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    poise::ChoiceParameter,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    /// The weight used for options of this rarity that don't set their own.
+    pub fn default_weight(self) -> f64 {
+        match self {
+            Rarity::Common => 100.0,
+            Rarity::Uncommon => 40.0,
+            Rarity::Rare => 12.0,
+            Rarity::Legendary => 3.0,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rarity::Common => "Common",
+            Rarity::Uncommon => "Uncommon",
+            Rarity::Rare => "Rare",
+            Rarity::Legendary => "Legendary",
+        }
+    }
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Rarity::Common => "⚪",
+            Rarity::Uncommon => "🟢",
+            Rarity::Rare => "🔵",
+            Rarity::Legendary => "🌟",
+        }
+    }
+}
+
+/// A single selectable option for a trait.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TraitOption {
+    pub label: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// Image drawn on fursona cards, relative to the assets directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<String>,
+    #[serde(default)]
+    pub rarity: Rarity,
+    /// Relative chance of being rolled, defaulting to the weight of the option's rarity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+    /// Values of options that must also be picked for this one to be allowed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+    /// Values of options that can't be picked alongside this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excludes: Vec<String>,
+    /// Species this option is limited to, or any species if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only_for_species: Vec<String>,
+}
+
+impl TraitOption {
+    /// Creates a common option with just a label and value.
+    pub fn new(label: String, value: String) -> Self {
+        Self {
+            label,
+            value,
+            description: None,
+            emoji: None,
+            sprite: None,
+            rarity: Rarity::default(),
+            weight: None,
+            requires: Vec::new(),
+            excludes: Vec::new(),
+            only_for_species: Vec::new(),
+        }
+    }
+
+    /// The value stored for an option when only its label is given, e.g. `maned_wolf`.
+    pub fn value_for(label: &str) -> String {
+        label.trim().to_lowercase().replace(' ', "_")
+    }
+
+    /// The relative chance of this option being rolled.
+    pub fn weight(&self) -> f64 {
+        self.weight.unwrap_or_else(|| self.rarity.default_weight())
+    }
+
+    /// Returns whether this option is available for a fursona's species, or either species of
+    /// a hybrid.
+    pub fn fits_species(&self, fursona: &Fursona) -> bool {
+        self.only_for_species.is_empty()
+            || fursona
+                .values(TraitKind::Species)
+                .iter()
+                .any(|s| self.only_for_species.iter().any(|o| o == s))
+    }
+
+    /// Builds the select menu entry for this option.
+    pub fn menu_option(&self) -> serenity::CreateSelectMenuOption {
+        let mut option = serenity::CreateSelectMenuOption::new(&self.label, &self.value);
+
+        if let Some(description) = &self.description {
+            option = option.description(description);
+        }
+
+        if let Some(emoji) = self.emoji.as_deref().and_then(reaction) {
+            option = option.emoji(emoji);
+        }
+
+        option
+    }
+
+    /// Explains what is wrong with the option's value or emoji, if they would break the menus
+    /// it is shown in.
+    pub fn problem(&self) -> Option<String> {
+        if wizard::is_reserved(&self.value) {
+            return Some(format!("the value `{}` is reserved", self.value));
+        }
+
+        match &self.emoji {
+            Some(emoji) if reaction(emoji).is_none() => {
+                Some(format!("`{emoji}` isn't a valid emoji"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parses an emoji typed in by an admin, either a standard emoji or a custom one such as
+/// `<:name:123>`.
+fn reaction(text: &str) -> Option<serenity::ReactionType> {
+    let text = text.trim();
+
+    if let Some(custom) = serenity::parse_emoji(text) {
+        return Some(serenity::ReactionType::Custom {
+            animated: custom.animated,
+            id: custom.id,
+            name: Some(custom.name),
+        });
+    }
+
+    emojis::get(text).map(|e| serenity::ReactionType::Unicode(e.as_str().to_string()))
+}
+
+/// The options available for one trait.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct TraitSet {
+    pub options: Vec<TraitOption>,
+    /// How many options are picked, defaulting to [`TraitKind::default_picks`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub picks: Option<Picks>,
+}
+
+impl TraitSet {
+    /// Finds an option by its stored value.
+    pub fn find(&self, value: &str) -> Option<&TraitOption> {
+        self.options.iter().find(|o| o.value == value)
+    }
+
+    /// Returns the chance of each option being picked in a single roll.
+    pub fn odds(&self) -> Vec<(&TraitOption, f64)> {
+        let total: f64 = self.options.iter().map(TraitOption::weight).sum();
+
+        self.options
+            .iter()
+            .map(|o| (o, if total > 0.0 { o.weight() / total } else { 0.0 }))
+            .collect()
+    }
+
+    /// Returns the label for a stored value, falling back to the value itself.
+    pub fn label<'a>(&'a self, value: &'a str) -> &'a str {
+        self.find(value).map_or(value, |o| o.label.as_str())
+    }
+}
+
+/// Every option available when creating a fursona.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct TraitCatalog {
+    pub species: TraitSet,
+    pub body_type: TraitSet,
+    pub markings: TraitSet,
+    pub accessories: TraitSet,
+    pub personality: TraitSet,
+    #[serde(default)]
+    pub hybrids: HybridConfig,
+    #[serde(default)]
+    pub card: CardConfig,
+}
+
+/// How hybrid species are offered and named, configured in the `[hybrids]` section.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct HybridConfig {
+    /// Whether fursonas can mix two species.
+    pub enabled: bool,
+    /// The chance of a random fursona being a hybrid, from 0 to 1.
+    pub chance: f64,
+    /// How hybrids without a custom name are named. `{first}` and `{second}` are replaced with
+    /// the labels of the species.
+    pub format: String,
+    /// Custom names for particular pairs of species, in either order.
+    pub names: Vec<HybridName>,
+}
+
+impl Default for HybridConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            chance: 0.1,
+            format: "{first}/{second} hybrid".to_string(),
+            names: Vec::new(),
+        }
+    }
+}
+
+/// A custom name for a hybrid of two species, e.g. "Wolfox".
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct HybridName {
+    pub species: [String; 2],
+    pub name: String,
+}
+
+impl TraitCatalog {
+    /// Loads a catalog from a TOML file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)?;
+        let catalog: TraitCatalog = toml::from_str(&contents)?;
+
+        for kind in TraitKind::ALL {
+            let set = catalog.get(kind);
+
+            if set.options.is_empty() {
+                return Err(format!("trait catalog has no {} options", kind.id()).into());
+            }
+
+            if let Some(problem) = catalog.picks(kind).validate(kind) {
+                return Err(problem.into());
+            }
+
+            if let Some(option) = set
+                .options
+                .iter()
+                .find(|o| !o.weight().is_finite() || o.weight() <= 0.0)
+            {
+                return Err(format!(
+                    "{} option `{}` must have a positive weight",
+                    kind.id(),
+                    option.value
+                )
+                .into());
+            }
+
+            for option in &set.options {
+                if let Some(problem) = option.problem() {
+                    return Err(
+                        format!("{} option `{}`: {problem}", kind.id(), option.value).into(),
+                    );
+                }
+
+                let unknown = option
+                    .requires
+                    .iter()
+                    .chain(&option.excludes)
+                    .find(|v| catalog.find(v).is_none())
+                    .or_else(|| {
+                        option
+                            .only_for_species
+                            .iter()
+                            .find(|v| catalog.species.find(v).is_none())
+                    });
+
+                if let Some(value) = unknown {
+                    return Err(format!(
+                        "{} option `{}` refers to unknown option `{value}`",
+                        kind.id(),
+                        option.value
+                    )
+                    .into());
+                }
+            }
+        }
+
+        if let Some(problem) = catalog.card.problem() {
+            return Err(problem.into());
+        }
+
+        let hybrids = &catalog.hybrids;
+
+        if !(0.0..=1.0).contains(&hybrids.chance) {
+            return Err("hybrid chance must be between 0 and 1".into());
+        }
+
+        if let Some(value) = hybrids
+            .names
+            .iter()
+            .flat_map(|n| &n.species)
+            .find(|v| catalog.species.find(v).is_none())
+        {
+            return Err(format!("hybrid name refers to unknown species `{value}`").into());
+        }
+
+        Ok(catalog)
+    }
+
+    /// Returns the name of a fursona's species, naming hybrids by the catalog's rules.
+    pub fn species_name(&self, fursona: &Fursona) -> String {
+        let first = self.species_label(fursona, &fursona.species);
+
+        let Some(second) = &fursona.hybrid_with else {
+            return first.to_string();
+        };
+
+        let custom = self.hybrids.names.iter().find(|n| {
+            let [a, b] = &n.species;
+
+            (a == &fursona.species && b == second) || (a == second && b == &fursona.species)
+        });
+
+        match custom {
+            Some(custom) => custom.name.clone(),
+            None => self
+                .hybrids
+                .format
+                .replace("{first}", &first)
+                .replace("{second}", &self.species_label(fursona, second)),
+        }
+    }
+
+    /// Returns the label of one of a fursona's species, marking custom species that haven't been
+    /// approved yet.
+    fn species_label(&self, fursona: &Fursona, value: &str) -> String {
+        if let Some(option) = self.species.find(value) {
+            return option.label.clone();
+        }
+
+        match &fursona.custom_species {
+            Some(custom) if custom.value == value => match &custom.rejected {
+                Some(reason) => format!("{} (rejected: {reason})", custom.label),
+                None => format!("{} (pending approval)", custom.label),
+            },
+            _ => value.to_string(),
+        }
+    }
+
+    /// Returns the label for a stored value of any trait, falling back to the value itself.
+    pub fn label<'a>(&'a self, value: &'a str) -> &'a str {
+        self.find(value).map_or(value, |o| o.label.as_str())
+    }
+
+    fn find(&self, value: &str) -> Option<&TraitOption> {
+        TraitKind::ALL
+            .into_iter()
+            .find_map(|kind| self.get(kind).find(value))
+    }
+
+    /// Returns the options a fursona has picked, skipping values no longer in the catalog.
+    fn picked<'a>(&'a self, fursona: &Fursona) -> Vec<&'a TraitOption> {
+        TraitKind::ALL
+            .into_iter()
+            .flat_map(|kind| {
+                let set = self.get(kind);
+
+                fursona
+                    .values(kind)
+                    .into_iter()
+                    .filter_map(|value| set.find(value))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns whether an option of a trait can be added to a partly built fursona without
+    /// breaking the species and exclusion rules. Requirements can only be checked once every
+    /// trait is picked, so they are left to [`TraitCatalog::conflicts`].
+    pub fn allows(&self, fursona: &Fursona, kind: TraitKind, option: &TraitOption) -> bool {
+        let picked = self.picked(fursona);
+
+        let fits = match kind {
+            // A species has to suit the options already picked, such as locked traits
+            TraitKind::Species => picked.iter().all(|p| {
+                p.only_for_species.is_empty() || p.only_for_species.contains(&option.value)
+            }),
+            _ => fursona.species.is_empty() || option.fits_species(fursona),
+        };
+
+        fits && !picked.into_iter().any(|picked| {
+            option.excludes.contains(&picked.value) || picked.excludes.contains(&option.value)
+        })
+    }
+
+    /// Returns the names of the options and hybrids whose rules mention a value.
+    pub fn referenced_by(&self, value: &str) -> Vec<String> {
+        let value = value.to_string();
+
+        let options = TraitKind::ALL
+            .into_iter()
+            .flat_map(|kind| &self.get(kind).options)
+            .filter(|o| {
+                o.requires.contains(&value)
+                    || o.excludes.contains(&value)
+                    || o.only_for_species.contains(&value)
+            })
+            .map(|o| o.label.clone());
+
+        let hybrids = self
+            .hybrids
+            .names
+            .iter()
+            .filter(|n| n.species.contains(&value))
+            .map(|n| n.name.clone());
+
+        options.chain(hybrids).collect()
+    }
+
+    /// Explains each compatibility rule a fursona breaks, or returns nothing if it follows them.
+    pub fn conflicts(&self, fursona: &Fursona) -> Vec<String> {
+        let picked = self.picked(fursona);
+        let has = |value: &String| picked.iter().any(|o| &o.value == value);
+
+        let mut conflicts = Vec::new();
+
+        for option in &picked {
+            if !option.fits_species(fursona) {
+                let species = option
+                    .only_for_species
+                    .iter()
+                    .map(|v| self.label(v).to_string())
+                    .collect::<Vec<_>>();
+
+                conflicts.push(format!(
+                    "**{}** is only available for {}",
+                    option.label,
+                    render::join_list(&species)
+                ));
+            }
+
+            for value in option.requires.iter().filter(|v| !has(v)) {
+                conflicts.push(format!(
+                    "**{}** requires **{}**",
+                    option.label,
+                    self.label(value)
+                ));
+            }
+
+            for value in option.excludes.iter().filter(|v| has(v)) {
+                conflicts.push(format!(
+                    "**{}** can't be combined with **{}**",
+                    option.label,
+                    self.label(value)
+                ));
+            }
+        }
+
+        conflicts
+    }
+
+    /// Returns how many options are picked for a trait.
+    pub fn picks(&self, kind: TraitKind) -> Picks {
+        self.get(kind)
+            .picks
+            .clone()
+            .unwrap_or_else(|| kind.default_picks())
+    }
+
+    pub fn get(&self, kind: TraitKind) -> &TraitSet {
+        match kind {
+            TraitKind::Species => &self.species,
+            TraitKind::BodyType => &self.body_type,
+            TraitKind::Markings => &self.markings,
+            TraitKind::Accessories => &self.accessories,
+            TraitKind::Personality => &self.personality,
+        }
+    }
+
+    pub fn get_mut(&mut self, kind: TraitKind) -> &mut TraitSet {
+        match kind {
+            TraitKind::Species => &mut self.species,
+            TraitKind::BodyType => &mut self.body_type,
+            TraitKind::Markings => &mut self.markings,
+            TraitKind::Accessories => &mut self.accessories,
+            TraitKind::Personality => &mut self.personality,
+        }
+    }
+}
+
+/// Trait catalogs customised by guild admins, falling back to the global default.
+pub struct GuildCatalogs {
+    default: TraitCatalog,
+    path: PathBuf,
+    guilds: Mutex<HashMap<serenity::GuildId, TraitCatalog>>,
+}
+
+impl GuildCatalogs {
+    /// Loads the guild catalogs stored at `path`, using `default` for every other guild.
+    pub fn open(default: TraitCatalog, path: PathBuf) -> Result<Self, Error> {
+        let guilds = store::load_json(&path)?.unwrap_or_default();
+
+        Ok(Self {
+            default,
+            path,
+            guilds: Mutex::new(guilds),
+        })
+    }
+
+    /// Returns the catalog for a guild, or the default outside of guilds.
+    pub fn get(&self, guild_id: Option<serenity::GuildId>) -> TraitCatalog {
+        let guilds = self.guilds.lock().unwrap();
+
+        guild_id
+            .and_then(|id| guilds.get(&id))
+            .unwrap_or(&self.default)
+            .clone()
+    }
+
+    /// Applies a change to a guild's catalog, starting from the default if it has none yet.
+    pub fn update<T>(
+        &self,
+        guild_id: serenity::GuildId,
+        f: impl FnOnce(&mut TraitCatalog) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut guilds = self.guilds.lock().unwrap();
+
+        let mut catalog = guilds
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_else(|| self.default.clone());
+
+        let result = f(&mut catalog)?;
+
+        guilds.insert(guild_id, catalog);
+        store::save_json(&self.path, &*guilds)?;
+
+        Ok(result)
+    }
+
+    /// Drops a guild's customisations, returning it to the default catalog.
+    pub fn reset(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        let mut guilds = self.guilds.lock().unwrap();
+
+        if guilds.remove(&guild_id).is_some() {
+            store::save_json(&self.path, &*guilds)?;
+        }
+
+        Ok(())
+    }
+}
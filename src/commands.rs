@@ -1,7 +1,32 @@
-use crate::{fursona::Fursona, Context, Error};
+mod catalog;
+mod edit;
+mod history;
+mod import;
+mod moderation;
+mod palette;
+mod privacy;
+
+pub use catalog::catalog;
+use edit::edit_fursona;
+use history::{history_fursona, restore_fursona, undo_fursona};
+use import::import_fursonas;
+pub use moderation::moderation;
+use palette::palette_fursona;
+pub use privacy::privacy;
+use privacy::visibility_fursona;
+
+use crate::{
+    card,
+    catalog::{TraitCatalog, TraitKind, TraitOption},
+    export,
+    fursona::{same_name, CustomSpecies, Fursona, Profile, Visibility},
+    generator, render,
+    wizard::{Outcome, Selections, Step, Wizard, CUSTOM_PREFIX},
+    Context, Error,
+};
 use poise::serenity_prelude as serenity;
-use rand::prelude::SliceRandom;
-use std::thread;
+use poise::Modal;
+use std::{collections::HashSet, thread};
 
 /// A simple ping command that responds with "Pong!" and the bot's latency.
 #[poise::command(slash_command)]
@@ -18,7 +43,23 @@ pub async fn ping(ctx: Context<'_>) -> Result<(), Error> {
 /// The parent command for fursona-related commands.
 #[poise::command(
     slash_command,
-    subcommands("view_fursona", "create_fursona", "random_fursona")
+    subcommands(
+        "view_fursona",
+        "card_fursona",
+        "create_fursona",
+        "random_fursona",
+        "details_fursona",
+        "edit_fursona",
+        "palette_fursona",
+        "visibility_fursona",
+        "history_fursona",
+        "undo_fursona",
+        "restore_fursona",
+        "list_fursonas",
+        "switch_fursona",
+        "delete_fursona",
+        "import_fursonas"
+    )
 )]
 pub async fn fursona(_: Context<'_>) -> Result<(), Error> {
     // This will never be called, because `subcommand_required` parameter is set
@@ -30,114 +71,507 @@ pub async fn fursona(_: Context<'_>) -> Result<(), Error> {
 pub async fn view_fursona(
     ctx: Context<'_>,
     #[description = "User to view fursona of"] user: Option<serenity::User>,
+    #[description = "Character to view, defaults to their active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
 ) -> Result<(), Error> {
-    let fursona = {
-        let fursonas = ctx.data().fursonas.lock().unwrap();
-        let u = user.as_ref().unwrap_or_else(|| ctx.author());
+    let u = user.as_ref().unwrap_or_else(|| ctx.author());
+    let profile = ctx.data().fursonas.get(u.id).await?.unwrap_or_default();
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
 
-        fursonas.get(&u.id).cloned()
-    };
+    let mut found = profile.find(name.as_deref());
 
-    if let Some(user) = user {
-        if let Some(fursona) = fursona {
-            // FIXME: #3 Formatting is incorrect
-            let response = format!(
-                "{}'s fursona is a **{}** with a **{}** body type, **{}** markings, and the following accessories: {:?}. Their personality is: **{}**",
-                user.name,
-                fursona.species,
-                fursona.body_type,
-                fursona.markings,
-                fursona.accessories,
-                fursona.personality,
-            );
-
-            ctx.say(response).await?;
-        } else {
-            ctx.say(format!("{} doesn't have a fursona set!", user.name))
-                .await?;
+    // Hidden characters are treated as missing, so their names aren't given away
+    if let Some(fursona) = found {
+        if !can_view(ctx, u.id, fursona.visibility).await {
+            found = None;
         }
+    }
+
+    if let Some(fursona) = found {
+        let mut embed = render::fursona_embed(fursona, u, &catalog);
+
+        // Only owners see their hidden characters, so they aren't shown to the channel
+        let mut reply =
+            poise::CreateReply::default().ephemeral(fursona.visibility != Visibility::Public);
+
+        if let Some(palette) = &fursona.palette {
+            let png = card::render_swatches(palette)?;
+
+            embed = embed.image("attachment://palette.png");
+            reply = reply.attachment(serenity::CreateAttachment::bytes(png, "palette.png"));
+        }
+
+        ctx.send(reply.embed(embed)).await?;
+    } else if let Some(name) = &name {
+        ctx.say(format!(
+            "{} doesn't have a fursona called **{name}**!",
+            u.name
+        ))
+        .await?;
+    } else if let Some(user) = &user {
+        ctx.say(format!("{} doesn't have a fursona set!", user.name))
+            .await?;
     } else {
-        if let Some(fursona) = fursona {
-            // FIXME: #2 Formatting is incorrect
-            let response = format!(
-                "Your fursona is a **{}** with a **{}** body type, **{}** markings, and the following accessories: {:?}. Their personality is: **{}**",
-                fursona.species,
-                fursona.body_type,
-                fursona.markings,
-                fursona.accessories,
-                fursona.personality,
-            );
-
-            ctx.say(response).await?;
-        } else {
-            let components = vec![serenity::CreateActionRow::Buttons(vec![
-                serenity::CreateButton::new("create_fursona")
-                    .label("Create Fursona")
-                    .style(serenity::ButtonStyle::Primary),
-                serenity::CreateButton::new("random_fursona")
-                    .label("Create Random Fursona")
-                    .style(serenity::ButtonStyle::Secondary),
-            ])];
+        let components = vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new("create_fursona")
+                .label("Create Fursona")
+                .style(serenity::ButtonStyle::Primary),
+            serenity::CreateButton::new("random_fursona")
+                .label("Create Random Fursona")
+                .style(serenity::ButtonStyle::Secondary),
+        ])];
 
-            let reply = poise::CreateReply::default()
-                .ephemeral(true)
-                .content("You don't have a fursona yet!")
-                .components(components);
+        let reply = poise::CreateReply::default()
+            .ephemeral(true)
+            .content("You don't have a fursona yet!")
+            .components(components);
 
-            let reply_msg = ctx.send(reply).await?;
+        let reply_msg = ctx.send(reply).await?;
 
-            // Wait for interaction
-            while let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
-                .author_id(ctx.author().id)
-                .channel_id(ctx.channel_id())
-                .timeout(std::time::Duration::from_secs(60))
-                .await
-            {
-                match interaction.data.custom_id.as_str() {
-                    "create_fursona" => {
-                        interaction.defer(ctx).await?;
+        let name = default_name(ctx);
 
-                        // Create a fursona with the user's input
-                        create_new_fursona(ctx, Some(reply_msg)).await?;
+        // Wait for interaction
+        while let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+            .author_id(ctx.author().id)
+            .channel_id(ctx.channel_id())
+            .timeout(std::time::Duration::from_secs(60))
+            .await
+        {
+            match interaction.data.custom_id.as_str() {
+                "create_fursona" => {
+                    let details = poise::execute_modal_on_component_interaction(
+                        ctx,
+                        interaction,
+                        Some(DetailsModal::new(name)),
+                        Some(std::time::Duration::from_secs(300)),
+                    )
+                    .await?;
+
+                    let Some(details) = details else {
+                        return Ok(());
+                    };
+
+                    if let Some(problem) = details.validate() {
+                        reply_msg
+                            .edit(
+                                ctx,
+                                poise::CreateReply::default()
+                                    .content(problem)
+                                    .components(vec![]),
+                            )
+                            .await?;
 
                         return Ok(());
                     }
-                    "random_fursona" => {
-                        interaction.defer(ctx).await?;
 
-                        // Create a random fursona
-                        create_random_fursona(ctx, Some(reply_msg)).await?;
+                    // Create a fursona with the user's input
+                    create_new_fursona(ctx, Some(reply_msg), details).await?;
 
-                        return Ok(());
-                    }
-                    _ => {}
+                    return Ok(());
                 }
+                "random_fursona" => {
+                    interaction.defer(ctx).await?;
+
+                    // Create a random fursona
+                    create_random_fursona(ctx, Some(reply_msg), name, None).await?;
+
+                    return Ok(());
+                }
+                _ => {}
             }
+        }
 
-            // Remove button once interaction times out
-            let reply = poise::CreateReply::default()
+        // Remove button once interaction times out
+        let reply = poise::CreateReply::default()
+            .ephemeral(true)
+            .content("You don't have a fursona yet!");
+
+        reply_msg.edit(ctx, reply).await?;
+    }
+
+    Ok(())
+}
+
+/// A command to render a user's fursona as a character card image.
+#[poise::command(slash_command, rename = "card")]
+pub async fn card_fursona(
+    ctx: Context<'_>,
+    #[description = "User to show the card of"] user: Option<serenity::User>,
+    #[description = "Character to show, defaults to their active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let u = user.as_ref().unwrap_or_else(|| ctx.author());
+    let profile = ctx.data().fursonas.get(u.id).await?.unwrap_or_default();
+
+    let fursona = match profile.find(name.as_deref()) {
+        Some(fursona) if can_view(ctx, u.id, fursona.visibility).await => fursona.clone(),
+        _ => {
+            ctx.say(format!("{} doesn't have that fursona!", u.name))
+                .await?;
+
+            return Ok(());
+        }
+    };
+
+    // Cards of characters that aren't public are only shown to the person asking
+    let hidden = fursona.visibility != Visibility::Public;
+
+    if hidden {
+        ctx.defer_ephemeral().await?;
+    } else {
+        ctx.defer().await?;
+    }
+
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+    let assets_dir = ctx.data().assets_dir.clone();
+
+    let png =
+        tokio::task::spawn_blocking(move || card::render_card(&fursona, &catalog, &assets_dir))
+            .await??;
+
+    let reply = poise::CreateReply::default()
+        .ephemeral(hidden)
+        .attachment(serenity::CreateAttachment::bytes(png, "fursona.png"));
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// A command to list a user's characters.
+#[poise::command(slash_command, rename = "list")]
+pub async fn list_fursonas(
+    ctx: Context<'_>,
+    #[description = "User to list the characters of"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let u = user.as_ref().unwrap_or_else(|| ctx.author());
+    let profile = ctx.data().fursonas.get(u.id).await?.unwrap_or_default();
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+
+    let mut visible = Vec::new();
+
+    for fursona in &profile.characters {
+        if can_view(ctx, u.id, fursona.visibility).await {
+            visible.push(fursona);
+        }
+    }
+
+    if visible.is_empty() {
+        ctx.say(format!("{} doesn't have a fursona set!", u.name))
+            .await?;
+
+        return Ok(());
+    }
+
+    let active = profile.active().map(|f| f.name.clone());
+
+    let description = visible
+        .iter()
+        .map(|f| {
+            let marker = if Some(&f.name) == active.as_ref() {
+                " (active)"
+            } else {
+                ""
+            };
+
+            // Only the owner sees how each character is shared
+            let visibility = if u.id == ctx.author().id && f.visibility != Visibility::Public {
+                format!(" {}", f.visibility.emoji())
+            } else {
+                String::new()
+            };
+
+            format!(
+                "**{}** - {}{marker}{visibility}",
+                f.name,
+                catalog.species_name(f)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // Counting every character would tell others how many are hidden from them
+    let count = if u.id == ctx.author().id {
+        profile.characters.len()
+    } else {
+        visible.len()
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("{}'s characters", u.display_name()))
+        .description(description)
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "{count} of {} characters",
+            ctx.data().max_characters
+        )));
+
+    // The list includes hidden characters when owners look at their own
+    let hidden = visible.iter().any(|f| f.visibility != Visibility::Public);
+
+    ctx.send(poise::CreateReply::default().ephemeral(hidden).embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// A command to change which of your characters is active.
+#[poise::command(slash_command, rename = "switch")]
+pub async fn switch_fursona(
+    ctx: Context<'_>,
+    #[description = "Character to switch to"]
+    #[autocomplete = "autocomplete_character"]
+    name: String,
+) -> Result<(), Error> {
+    let mut profile = author_profile(ctx).await?;
+
+    let response = if profile.switch(&name) {
+        ctx.data().fursonas.put(ctx.author().id, profile).await?;
+
+        format!("Switched to **{name}**!")
+    } else {
+        format!("You don't have a fursona called **{name}**!")
+    };
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(response),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// A command to delete one of your characters.
+#[poise::command(slash_command, rename = "delete")]
+pub async fn delete_fursona(
+    ctx: Context<'_>,
+    #[description = "Character to delete"]
+    #[autocomplete = "autocomplete_character"]
+    name: String,
+) -> Result<(), Error> {
+    let Some(fursona) = author_profile(ctx).await?.get(&name).cloned() else {
+        ctx.send(
+            poise::CreateReply::default()
                 .ephemeral(true)
-                .content("You don't have a fursona yet!");
+                .content(format!("You don't have a fursona called **{name}**!")),
+        )
+        .await?;
+
+        return Ok(());
+    };
 
-            reply_msg.edit(ctx, reply).await?;
+    let question = format!(
+        "Are you sure you want to delete **{}**? Its history will be deleted too, this can't be undone.",
+        fursona.name
+    );
+
+    let Some(msg) = confirm(ctx, question, "Delete").await? else {
+        return Ok(());
+    };
+
+    // Reload in case the profile changed while waiting
+    let mut profile = author_profile(ctx).await?;
+
+    let content = match profile.remove(&fursona.name) {
+        Some(removed) => {
+            ctx.data().fursonas.put(ctx.author().id, profile).await?;
+
+            format!("Deleted **{}**", removed.name)
         }
+        None => format!("You don't have a fursona called **{name}**!"),
+    };
+
+    msg.edit(
+        ctx,
+        poise::CreateReply::default()
+            .content(content)
+            .components(vec![]),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// A command to change the name, pronouns, bio and reference link of one of your characters.
+#[poise::command(slash_command, rename = "details")]
+pub async fn details_fursona(
+    ctx: Context<'_>,
+    #[description = "Character to change, defaults to your active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let poise::Context::Application(app_ctx) = ctx else {
+        return Ok(());
+    };
+
+    let Some(before) = author_profile(ctx).await?.find(name.as_deref()).cloned() else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("You don't have that fursona!"),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let Some(details) =
+        DetailsModal::execute_with_defaults(app_ctx, DetailsModal::from_fursona(&before)).await?
+    else {
+        return Ok(());
+    };
+
+    // Reload in case the profile changed while waiting
+    let mut profile = author_profile(ctx).await?;
+
+    let Some(mut fursona) = profile.get(&before.name).cloned() else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("You don't have that fursona!"),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let problem = details.validate().or_else(|| {
+        let renamed = !same_name(&details.name, &fursona.name);
+
+        (renamed && profile.get(&details.name).is_some())
+            .then(|| format!("You already have a fursona called **{}**!", details.name))
+    });
+
+    if let Some(problem) = problem {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(problem),
+        )
+        .await?;
+
+        return Ok(());
     }
 
+    let old_name = fursona.name.clone();
+    details.apply(&mut fursona);
+
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+    let embed = render::fursona_embed(&fursona, ctx.author(), &catalog);
+
+    profile.replace(&old_name, fursona);
+    ctx.data().fursonas.put(ctx.author().id, profile).await?;
+
+    let reply = poise::CreateReply::default()
+        .ephemeral(true)
+        .content("Fursona updated!")
+        .embed(embed);
+
+    ctx.send(reply).await?;
+
     Ok(())
 }
 
+/// The free-text details of a fursona, collected with a modal.
+#[derive(Debug, poise::Modal)]
+#[name = "About your fursona"]
+struct DetailsModal {
+    #[name = "Name"]
+    #[min_length = 1]
+    #[max_length = 32]
+    name: String,
+    #[name = "Pronouns"]
+    #[placeholder = "e.g. they/them"]
+    #[max_length = 32]
+    pronouns: Option<String>,
+    #[name = "Bio"]
+    #[paragraph]
+    #[max_length = 300]
+    bio: Option<String>,
+    #[name = "Reference link"]
+    #[placeholder = "https://"]
+    #[max_length = 200]
+    reference: Option<String>,
+}
+
+impl DetailsModal {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            pronouns: None,
+            bio: None,
+            reference: None,
+        }
+    }
+
+    fn from_fursona(fursona: &Fursona) -> Self {
+        Self {
+            name: fursona.name.clone(),
+            pronouns: fursona.pronouns.clone(),
+            bio: fursona.bio.clone(),
+            reference: fursona.reference.clone(),
+        }
+    }
+
+    /// Returns a message for the user if any of the details are invalid.
+    fn validate(&self) -> Option<String> {
+        if self.name.trim().is_empty() {
+            return Some("Your fursona needs a name!".to_string());
+        }
+
+        match self.reference.as_deref().map(str::trim) {
+            Some(link) if !link.is_empty() && !render::is_link(link) => {
+                Some("The reference link must be a full link starting with `https://`".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn apply(self, fursona: &mut Fursona) {
+        let non_empty =
+            |s: Option<String>| s.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+
+        fursona.name = self.name.trim().to_string();
+        fursona.pronouns = non_empty(self.pronouns);
+        fursona.bio = non_empty(self.bio);
+        fursona.reference = non_empty(self.reference);
+    }
+}
+
 /// A command to create a fursona.
 #[poise::command(slash_command, rename = "create")]
 pub async fn create_fursona(ctx: Context<'_>) -> Result<(), Error> {
-    // Check if user already has a fursona set
-    let fursona = {
-        let fursonas = ctx.data().fursonas.lock().unwrap();
-        let u = ctx.author();
+    let poise::Context::Application(app_ctx) = ctx else {
+        return Ok(());
+    };
 
-        fursonas.get(&u.id).cloned()
+    // Ask for the name and other details first, as a modal has to be the first response
+    let Some(details) =
+        DetailsModal::execute_with_defaults(app_ctx, DetailsModal::new(default_name(ctx))).await?
+    else {
+        return Ok(());
     };
 
-    if let Some(_) = fursona {
+    if let Some(problem) = details.validate() {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(problem),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let name = details.name.clone();
+
+    // Check if user already has a fursona with this name
+    let profile = author_profile(ctx).await?;
+
+    if profile.get(&name).is_some() {
         let reply = {
             let components = vec![serenity::CreateActionRow::Buttons(vec![
                 serenity::CreateButton::new("create_fursona")
@@ -150,7 +584,7 @@ pub async fn create_fursona(ctx: Context<'_>) -> Result<(), Error> {
 
             poise::CreateReply::default()
                 .ephemeral(true)
-                .content("You already have a fursona set!")
+                .content(format!("You already have a fursona called **{name}**!"))
                 .components(components)
         };
 
@@ -167,7 +601,7 @@ pub async fn create_fursona(ctx: Context<'_>) -> Result<(), Error> {
                     interaction.defer(ctx).await?;
 
                     // Create a fursona with the user's input
-                    create_new_fursona(ctx, Some(reply_msg)).await?;
+                    create_new_fursona(ctx, Some(reply_msg), details).await?;
 
                     return Ok(());
                 }
@@ -185,9 +619,11 @@ pub async fn create_fursona(ctx: Context<'_>) -> Result<(), Error> {
             .components(vec![]);
 
         reply_msg.edit(ctx, reply).await?;
+    } else if profile.characters.len() >= ctx.data().max_characters {
+        character_limit_reached(ctx).await?;
     } else {
         // Create a fursona with the user's input
-        create_new_fursona(ctx, None).await?;
+        create_new_fursona(ctx, None, details).await?;
     }
 
     Ok(())
@@ -196,6 +632,7 @@ pub async fn create_fursona(ctx: Context<'_>) -> Result<(), Error> {
 async fn create_new_fursona(
     ctx: Context<'_>,
     msg: Option<poise::ReplyHandle<'_>>,
+    details: DetailsModal,
 ) -> Result<(), Error> {
     let msg = match msg {
         Some(msg) => msg,
@@ -213,365 +650,371 @@ async fn create_new_fursona(
         }
     };
 
-    // Dropdowns for species, body type, accessories, markings, and personality
-    // REWRITE: This is a mess, refactor this into a more readable and maintainable state
-
-    // Species
-    let species_options = vec![
-        serenity::CreateSelectMenuOption::new("Dog", "dog"),
-        serenity::CreateSelectMenuOption::new("Cat", "cat"),
-        serenity::CreateSelectMenuOption::new("Fox", "fox"),
-        serenity::CreateSelectMenuOption::new("Wolf", "wolf"), // TODO: #4 Add more species
-    ];
-
-    let mut selected_species = String::new();
-
-    let species_page = poise::CreateReply::default()
-        .ephemeral(true)
-        .content("What species is your fursona?")
-        .components(vec![
-            serenity::CreateActionRow::SelectMenu(
-                serenity::CreateSelectMenu::new(
-                    "species",
-                    serenity::CreateSelectMenuKind::String {
-                        options: species_options,
-                    },
-                )
-                .placeholder("Select a species")
-                .min_values(1)
-                .max_values(1),
-            ),
-            serenity::CreateActionRow::Buttons(vec![
-                serenity::CreateButton::new("back")
-                    .label("Go Back")
-                    .style(serenity::ButtonStyle::Primary)
-                    .disabled(true),
-                serenity::CreateButton::new("next")
-                    .label("Next")
-                    .style(serenity::ButtonStyle::Success),
-                serenity::CreateButton::new("cancel")
-                    .label("Cancel")
-                    .style(serenity::ButtonStyle::Danger),
-            ]),
-        ]);
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
 
-    // body type
-    let body_type_options = vec![
-        serenity::CreateSelectMenuOption::new("Slim", "slim"),
-        serenity::CreateSelectMenuOption::new("Average", "average"),
-        serenity::CreateSelectMenuOption::new("Muscular", "muscular"),
-        serenity::CreateSelectMenuOption::new("Fluffy", "fluffy"),
-        serenity::CreateSelectMenuOption::new("Chubby", "chubby"),
-    ];
+    let steps = TraitKind::ALL
+        .iter()
+        .map(|&kind| trait_step(&catalog, kind, ctx.guild_id().is_some()))
+        .collect();
 
-    let mut selected_body_type = String::new();
+    let mut fursona = Fursona::new(
+        details.name.clone(),
+        String::new(),
+        String::new(),
+        String::new(),
+        Vec::new(),
+        String::new(),
+    );
 
-    let body_type_page = poise::CreateReply::default()
-        .ephemeral(true)
-        .content("What body type is your fursona?")
-        .components(vec![
-            serenity::CreateActionRow::SelectMenu(
-                serenity::CreateSelectMenu::new(
-                    "body_type",
-                    serenity::CreateSelectMenuKind::String {
-                        options: body_type_options,
-                    },
-                )
-                .placeholder("Select a body type")
-                .min_values(1)
-                .max_values(1),
-            ),
-            serenity::CreateActionRow::Buttons(vec![
-                serenity::CreateButton::new("back")
-                    .label("Go Back")
-                    .style(serenity::ButtonStyle::Primary),
-                serenity::CreateButton::new("next")
-                    .label("Next")
-                    .style(serenity::ButtonStyle::Success),
-                serenity::CreateButton::new("cancel")
-                    .label("Cancel")
-                    .style(serenity::ButtonStyle::Danger),
-            ]),
-        ]);
+    details.apply(&mut fursona);
 
-    // markings
-    let markings_options = vec![
-        serenity::CreateSelectMenuOption::new("None", "none"),
-        serenity::CreateSelectMenuOption::new("Stripes", "stripes"),
-        serenity::CreateSelectMenuOption::new("Spots", "spots"),
-        serenity::CreateSelectMenuOption::new("Solid", "solid"),
-    ];
+    let outcome = Wizard::new(steps)
+        .submit_label("Create Fursona")
+        .validate(compatibility(&catalog, &fursona, &TraitKind::ALL))
+        .run(ctx, &msg)
+        .await?;
 
-    let mut selected_markings = String::new();
+    match outcome {
+        Outcome::Completed(selections) => {
+            apply_selections(&catalog, &mut fursona, &selections, &TraitKind::ALL);
 
-    let markings_page = poise::CreateReply::default()
-        .ephemeral(true)
-        .content("What markings does your fursona have?")
-        .components(vec![
-            serenity::CreateActionRow::SelectMenu(
-                serenity::CreateSelectMenu::new(
-                    "markings",
-                    serenity::CreateSelectMenuKind::String {
-                        options: markings_options,
-                    },
-                )
-                .placeholder("Select markings")
-                .min_values(1)
-                .max_values(1),
-            ),
-            serenity::CreateActionRow::Buttons(vec![
-                serenity::CreateButton::new("back")
-                    .label("Go Back")
-                    .style(serenity::ButtonStyle::Primary),
-                serenity::CreateButton::new("next")
-                    .label("Next")
-                    .style(serenity::ButtonStyle::Success),
-                serenity::CreateButton::new("cancel")
-                    .label("Cancel")
-                    .style(serenity::ButtonStyle::Danger),
-            ]),
-        ]);
-
-    // accessories
-    let accessories_options = vec![
-        serenity::CreateSelectMenuOption::new("Glasses", "glasses"),
-        serenity::CreateSelectMenuOption::new("Scarf", "scarf"),
-        serenity::CreateSelectMenuOption::new("Hat", "hat"),
-        serenity::CreateSelectMenuOption::new("Collar", "collar"),
-        serenity::CreateSelectMenuOption::new("Jewellery", "jewellery"),
-        serenity::CreateSelectMenuOption::new("Wings", "wings"),
-        serenity::CreateSelectMenuOption::new("Tail", "tail"),
-        serenity::CreateSelectMenuOption::new("Horns", "horns"),
-    ];
-
-    let mut selected_accessories = Vec::new();
-
-    let accessories_page = poise::CreateReply::default()
-        .ephemeral(true)
-        .content("What accessories does your fursona have?")
-        .components(vec![
-            serenity::CreateActionRow::SelectMenu(
-                serenity::CreateSelectMenu::new(
-                    "accessories",
-                    serenity::CreateSelectMenuKind::String {
-                        options: accessories_options,
-                    },
-                )
-                .placeholder("Select accessories")
-                .min_values(0)
-                .max_values(3),
-            ),
-            serenity::CreateActionRow::Buttons(vec![
-                serenity::CreateButton::new("back")
-                    .label("Go Back")
-                    .style(serenity::ButtonStyle::Primary),
-                serenity::CreateButton::new("next")
-                    .label("Next")
-                    .style(serenity::ButtonStyle::Success),
-                serenity::CreateButton::new("cancel")
-                    .label("Cancel")
-                    .style(serenity::ButtonStyle::Danger),
-            ]),
-        ]);
+            let embed = render::fursona_embed(&fursona, ctx.author(), &catalog);
 
-    // personality
-    let personality_options = vec![
-        serenity::CreateSelectMenuOption::new("Shy", "shy"),
-        serenity::CreateSelectMenuOption::new("Friendly", "friendly"),
-        serenity::CreateSelectMenuOption::new("Silly", "silly"),
-        serenity::CreateSelectMenuOption::new("Brave", "brave"),
-        serenity::CreateSelectMenuOption::new("Caring", "caring"),
-        serenity::CreateSelectMenuOption::new("Mischievous", "mischievous"),
-    ];
+            let mut content = "Fursona created!".to_string();
 
-    let mut selected_personality = String::new();
+            if let Some(note) = moderation::submit_species(ctx, &fursona).await? {
+                content.push_str(&format!("\n{note}"));
+            }
 
-    let personality_page = poise::CreateReply::default()
-        .ephemeral(true)
-        .content("What is your fursona's personality?")
-        .components(vec![
-            serenity::CreateActionRow::SelectMenu(
-                serenity::CreateSelectMenu::new(
-                    "personality",
-                    serenity::CreateSelectMenuKind::String {
-                        options: personality_options,
-                    },
-                )
-                .placeholder("Select personality")
-                .min_values(1)
-                .max_values(1),
-            ),
-            serenity::CreateActionRow::Buttons(vec![
-                serenity::CreateButton::new("back")
-                    .label("Go Back")
-                    .style(serenity::ButtonStyle::Primary),
-                serenity::CreateButton::new("create")
-                    .label("Create Fursona")
-                    .style(serenity::ButtonStyle::Success),
-                serenity::CreateButton::new("cancel")
-                    .label("Cancel")
-                    .style(serenity::ButtonStyle::Danger),
-            ]),
-        ]);
+            save_character(ctx, fursona).await?;
 
-    // page management
+            let reply = poise::CreateReply::default()
+                .ephemeral(true)
+                .content(content)
+                .embed(embed)
+                .components(vec![]);
 
-    let mut page = 0;
-    let pages = vec![
-        species_page,
-        body_type_page,
-        markings_page,
-        accessories_page,
-        personality_page,
-    ];
+            msg.edit(ctx, reply).await?;
+        }
+        Outcome::Cancelled => cancel_action(ctx, msg).await?,
+        Outcome::TimedOut => {
+            let reply = poise::CreateReply::default()
+                .content("Timed out")
+                .components(vec![]);
 
-    // Reply to the user with the first page listen for interactions
+            msg.edit(ctx, reply).await?;
+        }
+    }
 
-    let reply = pages[page].clone();
+    Ok(())
+}
 
-    msg.edit(ctx, reply).await?;
+/// Copies the traits picked in a wizard onto a fursona.
+///
+/// Species typed in through "Other…" use the catalog's option if there is one with that name,
+/// otherwise they are kept as a custom species waiting for review.
+fn apply_selections(
+    catalog: &TraitCatalog,
+    fursona: &mut Fursona,
+    selections: &Selections,
+    kinds: &[TraitKind],
+) {
+    for &kind in kinds {
+        let mut custom = None;
+
+        let values = selections
+            .many(kind.id())
+            .into_iter()
+            .map(|value| {
+                let Some(label) = value.strip_prefix(CUSTOM_PREFIX) else {
+                    return value;
+                };
 
-    while let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
-        .author_id(ctx.author().id)
-        .channel_id(ctx.channel_id())
-        .timeout(std::time::Duration::from_secs(300))
-        .await
-    {
-        match interaction.data.custom_id.as_str() {
-            "species" => {
-                selected_species = match &interaction.data.kind {
-                    serenity::ComponentInteractionDataKind::StringSelect { values } => {
-                        values[0].clone()
+                let value = TraitOption::value_for(label);
+
+                let existing = catalog
+                    .species
+                    .options
+                    .iter()
+                    .find(|o| o.value == value || o.label.eq_ignore_ascii_case(label));
+
+                match existing {
+                    Some(option) => option.value.clone(),
+                    None => {
+                        // Picking the same species again keeps the moderators' decision on it
+                        let previous = fursona
+                            .custom_species
+                            .as_ref()
+                            .filter(|c| c.value == value)
+                            .cloned();
+
+                        custom = Some(previous.unwrap_or_else(|| CustomSpecies {
+                            value: value.clone(),
+                            label: label.to_string(),
+                            rejected: None,
+                        }));
+
+                        value
                     }
-                    _ => panic!("unexpected interaction data kind"),
-                };
+                }
+            })
+            .collect();
 
-                let reply = pages[page].clone().content(format!(
-                    "What species is your fursona? Selected: {selected_species}"
-                ));
+        fursona.set_values(kind, values);
 
-                msg.edit(ctx, reply).await?;
+        if custom.is_some() {
+            fursona.custom_species = custom;
+        }
+    }
+}
+
+/// Returns the values picked for a trait as wizard selections, with custom species turned back
+/// into typed in values.
+fn wizard_values(fursona: &Fursona, kind: TraitKind) -> Vec<String> {
+    fursona
+        .values(kind)
+        .into_iter()
+        .map(|value| match &fursona.custom_species {
+            Some(custom) if kind == TraitKind::Species && custom.value == value => {
+                format!("{CUSTOM_PREFIX}{}", custom.label)
             }
-            "body_type" => {
-                selected_body_type = match &interaction.data.kind {
-                    serenity::ComponentInteractionDataKind::StringSelect { values } => {
-                        values[0].clone()
-                    }
-                    _ => panic!("unexpected interaction data kind"),
-                };
+            _ => value.to_string(),
+        })
+        .collect()
+}
 
-                let reply = pages[page].clone().content(format!(
-                    "What body type is your fursona? Selected: {selected_body_type}"
-                ));
+/// Builds a wizard validator rejecting picks that break the catalog's compatibility rules when
+/// applied to `fursona`.
+fn compatibility(
+    catalog: &TraitCatalog,
+    fursona: &Fursona,
+    kinds: &[TraitKind],
+) -> impl Fn(&Selections) -> Option<String> + Send + Sync + 'static {
+    let catalog = catalog.clone();
+    let fursona = fursona.clone();
+    let kinds = kinds.to_vec();
+
+    move |selections| {
+        let mut picked = fursona.clone();
+        apply_selections(&catalog, &mut picked, selections, &kinds);
+
+        let conflicts = catalog.conflicts(&picked);
+
+        (!conflicts.is_empty()).then(|| {
+            format!(
+                "That combination isn't allowed:\n- {}",
+                conflicts.join("\n- ")
+            )
+        })
+    }
+}
 
-                msg.edit(ctx, reply).await?;
-            }
-            "markings" => {
-                selected_markings = match &interaction.data.kind {
-                    serenity::ComponentInteractionDataKind::StringSelect { values } => {
-                        values[0].clone()
-                    }
-                    _ => panic!("unexpected interaction data kind"),
-                };
+/// Builds the wizard step for picking one trait from the catalog. With `custom`, users can type
+/// in a species that isn't in the catalog.
+fn trait_step(catalog: &TraitCatalog, kind: TraitKind, custom: bool) -> Step {
+    let options = catalog.get(kind).options.clone();
+    let picks = catalog.picks(kind);
+
+    let step = match kind {
+        TraitKind::Species => Step::new(
+            kind.id(),
+            kind.name(),
+            "What species is your fursona?",
+            "Select a species",
+            options,
+        ),
+        TraitKind::BodyType => Step::new(
+            kind.id(),
+            kind.name(),
+            "What body type is your fursona?",
+            "Select a body type",
+            options,
+        ),
+        TraitKind::Markings => Step::new(
+            kind.id(),
+            kind.name(),
+            "What markings does your fursona have?",
+            "Select markings",
+            options,
+        ),
+        TraitKind::Accessories => Step::new(
+            kind.id(),
+            kind.name(),
+            "What accessories does your fursona have?",
+            "Select accessories",
+            options,
+        ),
+        TraitKind::Personality => Step::new(
+            kind.id(),
+            kind.name(),
+            "What is your fursona's personality?",
+            "Select personality",
+            options,
+        ),
+    };
 
-                let reply = pages[page].clone().content(format!(
-                    "What markings does your fursona have? Selected: {selected_markings}"
-                ));
+    let step = step.values(picks.min, picks.max).required(picks.min > 0);
 
-                msg.edit(ctx, reply).await?;
-            }
-            "accessories" => {
-                selected_accessories = match &interaction.data.kind {
-                    serenity::ComponentInteractionDataKind::StringSelect { values } => {
-                        values.clone()
-                    }
-                    _ => panic!("unexpected interaction data kind"),
-                };
+    match kind {
+        TraitKind::Species if catalog.hybrids.enabled => {
+            step.other(custom).expandable(2, "🧬 Hybrid")
+        }
+        TraitKind::Species => step.other(custom),
+        _ => step,
+    }
+}
 
-                // FIXME: #5 Formatting is incorrect
-                let reply = pages[page].clone().content(format!(
-                    "What accessories does your fursona have? Selected: {:?}",
-                    selected_accessories
-                ));
+/// Returns the author's profile, or an empty one if they haven't created a fursona yet.
+async fn author_profile(ctx: Context<'_>) -> Result<Profile, Error> {
+    Ok(ctx
+        .data()
+        .fursonas
+        .get(ctx.author().id)
+        .await?
+        .unwrap_or_default())
+}
 
-                msg.edit(ctx, reply).await?;
-            }
-            "personality" => {
-                selected_personality = match &interaction.data.kind {
-                    serenity::ComponentInteractionDataKind::StringSelect { values } => {
-                        values[0].clone()
-                    }
-                    _ => panic!("unexpected interaction data kind"),
-                };
+/// Adds a character to the author's profile, replacing any with the same name.
+async fn save_character(ctx: Context<'_>, fursona: Fursona) -> Result<(), Error> {
+    let mut profile = author_profile(ctx).await?;
 
-                let reply = pages[page].clone().content(format!(
-                    "What is your fursona's personality? Selected: {selected_personality}"
-                ));
+    profile.upsert(fursona);
 
-                msg.edit(ctx, reply).await?;
-            }
-            "back" => {
-                if page > 0 {
-                    page -= 1;
-                }
+    ctx.data().fursonas.put(ctx.author().id, profile).await
+}
 
-                // FIXME: #6 This is will not display the selected values
-                let reply = pages[page].clone();
+/// The name given to a character when the user doesn't pick one.
+fn default_name(ctx: Context<'_>) -> String {
+    ctx.author().display_name().to_string()
+}
 
-                msg.edit(ctx, reply).await?;
-            }
-            "next" => {
-                if page < pages.len() - 1 {
-                    page += 1;
-                }
+async fn character_limit_reached(ctx: Context<'_>) -> Result<(), Error> {
+    let reply = poise::CreateReply::default()
+        .ephemeral(true)
+        .content(format!(
+        "You already have the maximum of {} characters! Delete one with `/fursona delete` first.",
+        ctx.data().max_characters
+    ));
 
-                let reply = pages[page].clone();
+    ctx.send(reply).await?;
 
-                msg.edit(ctx, reply).await?;
-            }
-            "cancel" => {
-                cancel_action(ctx, msg).await?;
+    Ok(())
+}
 
-                return Ok(());
-            }
-            "create" => {
-                // Create the fursona
-                let fursona = Fursona::new(
-                    selected_species,
-                    selected_body_type,
-                    selected_markings,
-                    selected_accessories,
-                    selected_personality,
-                );
+/// Suggests the characters of the user picked in the command, or of the author.
+async fn autocomplete_character(
+    ctx: Context<'_>,
+    partial: &str,
+) -> Vec<serenity::AutocompleteChoice> {
+    let user_id = match ctx {
+        poise::Context::Application(app) => option_user(&app.interaction.data.options()),
+        _ => None,
+    }
+    .unwrap_or(ctx.author().id);
 
-                {
-                    let mut fursonas = ctx.data().fursonas.lock().unwrap();
+    let Ok(Some(profile)) = ctx.data().fursonas.get(user_id).await else {
+        return vec![];
+    };
 
-                    fursonas.insert(ctx.author().id, fursona);
-                }
+    let partial = partial.to_lowercase();
 
-                let reply = poise::CreateReply::default()
-                    .ephemeral(true)
-                    .content("Fursona created!")
-                    .components(vec![]);
+    let mut choices = Vec::new();
 
-                msg.edit(ctx, reply).await?;
+    for fursona in &profile.characters {
+        if choices.len() == 25 {
+            break;
+        }
 
-                return Ok(());
-            }
-            _ => {}
+        if fursona.name.to_lowercase().contains(&partial)
+            && can_view(ctx, user_id, fursona.visibility).await
+        {
+            choices.push(serenity::AutocompleteChoice::new(
+                fursona.name.clone(),
+                fursona.name.clone(),
+            ));
         }
+    }
 
-        interaction.defer(ctx).await?;
+    choices
+}
+
+/// Returns whether the author may see a character with the given visibility, belonging to
+/// `owner`. Owners can always see their own characters.
+async fn can_view(ctx: Context<'_>, owner: serenity::UserId, visibility: Visibility) -> bool {
+    if owner == ctx.author().id {
+        return true;
     }
 
-    let reply = poise::CreateReply::default()
-        .content("Timed out")
-        .components(vec![]);
+    match visibility {
+        Visibility::Public => true,
+        Visibility::Server => match ctx.guild_id() {
+            Some(guild_id) => guild_id.member(ctx, owner).await.is_ok(),
+            None => false,
+        },
+        Visibility::Private => false,
+    }
+}
 
-    msg.edit(ctx, reply).await?;
+/// Finds the `user` option of a (sub)command interaction.
+fn option_user(options: &[serenity::ResolvedOption]) -> Option<serenity::UserId> {
+    options.iter().find_map(|option| match &option.value {
+        serenity::ResolvedValue::User(user, _) if option.name == "user" => Some(user.id),
+        serenity::ResolvedValue::SubCommand(options)
+        | serenity::ResolvedValue::SubCommandGroup(options) => option_user(options),
+        _ => None,
+    })
+}
 
-    Ok(())
+/// Asks the user to confirm a destructive action, returning the message to reply in if they do.
+async fn confirm<'a>(
+    ctx: Context<'a>,
+    question: String,
+    label: &str,
+) -> Result<Option<poise::ReplyHandle<'a>>, Error> {
+    let components = vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new("confirm")
+            .label(label)
+            .style(serenity::ButtonStyle::Danger),
+        serenity::CreateButton::new("cancel")
+            .label("Cancel")
+            .style(serenity::ButtonStyle::Secondary),
+    ])];
+
+    let msg = ctx
+        .send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(question)
+                .components(components),
+        )
+        .await?;
+
+    let message_id = msg.message().await?.id;
+
+    let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .message_id(message_id)
+        .timeout(std::time::Duration::from_secs(60))
+        .await
+    else {
+        let reply = poise::CreateReply::default()
+            .content("Timed out")
+            .components(vec![]);
+
+        msg.edit(ctx, reply).await?;
+
+        return Ok(None);
+    };
+
+    interaction.defer(ctx).await?;
+
+    if interaction.data.custom_id != "confirm" {
+        cancel_action(ctx, msg).await?;
+
+        return Ok(None);
+    }
+
+    Ok(Some(msg))
 }
 
 async fn cancel_action(ctx: Context<'_>, msg: poise::ReplyHandle<'_>) -> Result<(), Error> {
@@ -586,16 +1029,40 @@ async fn cancel_action(ctx: Context<'_>, msg: poise::ReplyHandle<'_>) -> Result<
 
 /// A command to generate a random fursona.
 #[poise::command(slash_command, rename = "random")]
-pub async fn random_fursona(ctx: Context<'_>) -> Result<(), Error> {
-    // Check if user already has a fursona set
-    let fursona = {
-        let fursonas = ctx.data().fursonas.lock().unwrap();
-        let u = ctx.author();
+pub async fn random_fursona(
+    ctx: Context<'_>,
+    #[description = "Name of the character, defaults to your display name"]
+    #[max_length = 32]
+    name: Option<String>,
+    #[description = "Any text, the same seed always rolls the same fursona"]
+    #[max_length = 100]
+    seed: Option<String>,
+    #[description = "Roll the fursona destined for you, which is always the same"] destined: Option<
+        bool,
+    >,
+) -> Result<(), Error> {
+    let name = name.unwrap_or_else(|| default_name(ctx));
 
-        fursonas.get(&u.id).cloned()
+    let seed = match (seed, destined.unwrap_or(false)) {
+        (Some(_), true) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .ephemeral(true)
+                    .content("A destined fursona can't have a seed, pick one or the other!"),
+            )
+            .await?;
+
+            return Ok(());
+        }
+        (Some(seed), false) => Some(Seed::Text(seed)),
+        (None, true) => Some(Seed::Destined),
+        (None, false) => None,
     };
 
-    if let Some(_) = fursona {
+    // Check if user already has a fursona with this name
+    let profile = author_profile(ctx).await?;
+
+    if profile.get(&name).is_some() {
         let reply = {
             let components = vec![serenity::CreateActionRow::Buttons(vec![
                 serenity::CreateButton::new("create_fursona")
@@ -608,7 +1075,7 @@ pub async fn random_fursona(ctx: Context<'_>) -> Result<(), Error> {
 
             poise::CreateReply::default()
                 .ephemeral(true)
-                .content("You already have a fursona set!")
+                .content(format!("You already have a fursona called **{name}**!"))
                 .components(components)
         };
 
@@ -625,7 +1092,7 @@ pub async fn random_fursona(ctx: Context<'_>) -> Result<(), Error> {
                     interaction.defer(ctx).await?;
 
                     // Create a random fursona
-                    create_random_fursona(ctx, Some(reply_msg)).await?;
+                    create_random_fursona(ctx, Some(reply_msg), name, seed).await?;
 
                     return Ok(());
                 }
@@ -643,17 +1110,45 @@ pub async fn random_fursona(ctx: Context<'_>) -> Result<(), Error> {
             .components(vec![]);
 
         reply_msg.edit(ctx, reply).await?;
+    } else if profile.characters.len() >= ctx.data().max_characters {
+        character_limit_reached(ctx).await?;
     } else {
         // Create a random fursona
-        create_random_fursona(ctx, None).await?;
+        create_random_fursona(ctx, None, name, seed).await?;
     }
 
     Ok(())
 }
 
+/// Where the rolls of a random fursona come from when they should be reproducible.
+enum Seed {
+    /// Text picked by the user, which can be shared to roll the same fursona again.
+    Text(String),
+    /// The user's id, giving everyone their own fursona.
+    Destined,
+}
+
+impl Seed {
+    fn value(&self, user_id: serenity::UserId) -> u64 {
+        match self {
+            Seed::Text(text) => generator::seed(text),
+            Seed::Destined => generator::destined_seed(user_id),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Seed::Text(text) => format!("Seed: `{text}`"),
+            Seed::Destined => "This is your destined fursona!".to_string(),
+        }
+    }
+}
+
 async fn create_random_fursona(
     ctx: Context<'_>,
     msg: Option<poise::ReplyHandle<'_>>,
+    name: String,
+    seed: Option<Seed>,
 ) -> Result<(), Error> {
     let msg = match msg {
         Some(msg) => {
@@ -680,42 +1175,94 @@ async fn create_random_fursona(
         }
     };
 
-    // Generate a random fursona
-    let species = ["Dog", "Cat", "Fox", "Wolf"];
-    let body_type = ["Slim", "Average", "Muscular", "Fluffy", "Chubby"];
-    let markings = ["None", "Stripes", "Spots", "Solid"];
-    let accessories = [
-        "Glasses",
-        "Scarf",
-        "Hat",
-        "Collar",
-        "Jewellery",
-        "Wings",
-        "Tail",
-        "Horns",
-    ];
-    let personality = ["Shy", "Friendly", "Silly", "Brave", "Caring", "Mischievous"];
-
-    let fursona = Fursona::new(
-        species[rand::random::<usize>() % species.len()].to_string(),
-        body_type[rand::random::<usize>() % body_type.len()].to_string(),
-        markings[rand::random::<usize>() % markings.len()].to_string(),
-        accessories
-            .choose_multiple(&mut rand::thread_rng(), rand::random::<usize>() % 3)
-            .map(|s| s.to_string())
-            .collect(),
-        personality[rand::random::<usize>() % personality.len()].to_string(),
-    );
+    // Roll a fursona and let the user reroll it until they are happy with it
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+
+    // Seeded rolls are reproducible, rerolls included
+    let mut rng = generator::seeded_rng(match &seed {
+        Some(seed) => seed.value(ctx.author().id),
+        None => rand::random(),
+    });
+    let note = seed.as_ref().map(Seed::describe);
+
+    let mut locked = HashSet::new();
+    let mut fursona = roll_fursona(&catalog, &mut rng, &name, &locked, None);
 
+    msg.edit(
+        ctx,
+        random_preview(ctx, &catalog, &fursona, &locked, note.as_deref()),
+    )
+    .await?;
+
+    let message_id = msg.message().await?.id;
+
+    while let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .message_id(message_id)
+        .timeout(std::time::Duration::from_secs(300))
+        .await
     {
-        let mut fursonas = ctx.data().fursonas.lock().unwrap();
+        match interaction.data.custom_id.as_str() {
+            "reroll" => {
+                fursona = roll_fursona(&catalog, &mut rng, &name, &locked, Some(&fursona));
+            }
+            "accept" => {
+                interaction.defer(ctx).await?;
+
+                // The generator gives up on rolls it can't make follow the rules
+                if !catalog.conflicts(&fursona).is_empty() {
+                    continue;
+                }
 
-        fursonas.insert(ctx.author().id, fursona);
+                let embed = render::fursona_embed(&fursona, ctx.author(), &catalog);
+                let rarity = generator::rarity(&catalog, &fursona);
+
+                save_character(ctx, fursona).await?;
+
+                let reply = poise::CreateReply::default()
+                    .ephemeral(true)
+                    .content(format!(
+                        "Random Fursona created! Rarity: {} **{}**",
+                        rarity.emoji(),
+                        rarity.name()
+                    ))
+                    .embed(embed)
+                    .components(vec![]);
+
+                msg.edit(ctx, reply).await?;
+
+                return Ok(());
+            }
+            "discard" => {
+                interaction.defer(ctx).await?;
+
+                cancel_action(ctx, msg).await?;
+
+                return Ok(());
+            }
+            id => {
+                if let Some(kind) = id
+                    .strip_prefix("lock_")
+                    .and_then(|id| TraitKind::ALL.into_iter().find(|k| k.id() == id))
+                {
+                    if !locked.remove(&kind) {
+                        locked.insert(kind);
+                    }
+                }
+            }
+        }
+
+        msg.edit(
+            ctx,
+            random_preview(ctx, &catalog, &fursona, &locked, note.as_deref()),
+        )
+        .await?;
+
+        interaction.defer(ctx).await?;
     }
 
     let reply = poise::CreateReply::default()
-        .ephemeral(true)
-        .content("Random Fursona created!")
+        .content("Timed out")
         .components(vec![]);
 
     msg.edit(ctx, reply).await?;
@@ -723,35 +1270,182 @@ async fn create_random_fursona(
     Ok(())
 }
 
+/// Rolls a random fursona, keeping the locked traits of the previous roll.
+fn roll_fursona(
+    catalog: &TraitCatalog,
+    rng: &mut impl rand::Rng,
+    name: &str,
+    locked: &HashSet<TraitKind>,
+    previous: Option<&Fursona>,
+) -> Fursona {
+    let locked = previous
+        .map(|previous| {
+            locked
+                .iter()
+                .map(|&kind| {
+                    let values = previous.values(kind).into_iter().map(String::from);
+
+                    (kind, values.collect())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut fursona = generator::generate(catalog, rng, &locked);
+    fursona.name = name.to_string();
+
+    fursona
+}
+
+/// Shows a rolled fursona with buttons to lock traits, reroll, accept or discard it.
+fn random_preview(
+    ctx: Context<'_>,
+    catalog: &TraitCatalog,
+    fursona: &Fursona,
+    locked: &HashSet<TraitKind>,
+    note: Option<&str>,
+) -> poise::CreateReply {
+    let locks = TraitKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let (emoji, style) = if locked.contains(&kind) {
+                ("🔒", serenity::ButtonStyle::Primary)
+            } else {
+                ("🔓", serenity::ButtonStyle::Secondary)
+            };
+
+            serenity::CreateButton::new(format!("lock_{}", kind.id()))
+                .label(format!("{emoji} {}", kind.name()))
+                .style(style)
+        })
+        .collect();
+
+    let rarity = generator::rarity(catalog, fursona);
+
+    let mut content = format!(
+        "Here's your random fursona! Rarity: {} **{}**\n",
+        rarity.emoji(),
+        rarity.name()
+    );
+
+    if let Some(note) = note {
+        content.push_str(&format!("{note}\n"));
+    }
+
+    content.push_str("Lock the traits you like and reroll the rest.");
+
+    let conflicts = catalog.conflicts(fursona);
+
+    if !conflicts.is_empty() {
+        content.push_str(&format!(
+            "\n:warning: This roll breaks the catalog's rules, reroll or unlock some traits:\n- {}",
+            conflicts.join("\n- ")
+        ));
+    }
+
+    poise::CreateReply::default()
+        .ephemeral(true)
+        .content(content)
+        .embed(render::fursona_embed(fursona, ctx.author(), catalog))
+        .components(vec![
+            serenity::CreateActionRow::Buttons(locks),
+            serenity::CreateActionRow::Buttons(vec![
+                serenity::CreateButton::new("reroll")
+                    .label("Reroll")
+                    .style(serenity::ButtonStyle::Primary),
+                serenity::CreateButton::new("accept")
+                    .label("Accept")
+                    .style(serenity::ButtonStyle::Success)
+                    .disabled(!conflicts.is_empty()),
+                serenity::CreateButton::new("discard")
+                    .label("Discard")
+                    .style(serenity::ButtonStyle::Danger),
+            ]),
+        ])
+}
+
 /// A command to export all fursonas.
-#[poise::command(slash_command)]
-pub async fn export_fursonas(ctx: Context<'_>) -> Result<(), Error> {
-    let fursonas = {
-        let fursonas = ctx.data().fursonas.lock().unwrap();
+#[poise::command(slash_command, guild_only)]
+pub async fn export_fursonas(
+    ctx: Context<'_>,
+    #[description = "File format, defaults to JSON"] format: Option<export::Format>,
+) -> Result<(), Error> {
+    let format = format.unwrap_or_default();
+    let guild_id = ctx.guild_id().unwrap();
+    let settings = ctx.data().settings.get(guild_id);
 
-        fursonas.clone()
+    let allowed = match ctx.author_member().await {
+        Some(member) => settings.can_export(&member),
+        None => false,
     };
 
-    let mut export = Vec::new();
+    if !allowed {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(format!(
+                    "You need the {} permission or an allowed role to export fursonas",
+                    settings.export_permission.name()
+                )),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    // Looking up members can take a while
+    ctx.defer_ephemeral().await?;
+
+    let fursonas = ctx.data().fursonas.list().await?;
+
+    let mut entries = Vec::new();
+
+    // Users who opted out are left out entirely, and only public characters are exported
+    for (user_id, profile) in fursonas
+        .iter()
+        .filter(|(_, profile)| !profile.exclude_from_exports)
+    {
+        let public = profile
+            .characters
+            .iter()
+            .filter(|f| f.visibility == Visibility::Public)
+            .collect::<Vec<_>>();
+
+        if public.is_empty() {
+            continue;
+        }
 
-    for (user_id, fursona) in fursonas.iter() {
-        // Convert user ids into usernames
-        let username = match ctx.http().get_user(*user_id).await {
-            Ok(user) => user.name,
-            Err(_) => format!("Unknown User ({})", user_id),
+        // Characters are stored for every server, so only members of this one are exported
+        let Ok(member) = guild_id.member(ctx, *user_id).await else {
+            continue;
         };
 
-        export.push((username, fursona));
+        let username = member.user.name.clone();
+
+        for fursona in public {
+            entries.push(export::ExportedFursona::new(
+                *user_id,
+                username.clone(),
+                fursona,
+            ));
+        }
     }
 
-    // Export as a json file
-    let json_export = serde_json::to_string(&export)?;
-    let file_name = "fursonas.json";
-    let file_content = serenity::CreateAttachment::bytes(json_export.as_bytes(), file_name);
+    let catalog = ctx.data().catalogs.get(Some(guild_id));
+    let contents = export::export(format, &entries, &catalog)?;
+    let file_name = format!("fursonas.{}", format.extension());
+
+    ctx.data().audit.record(
+        Some(guild_id),
+        ctx.author().id,
+        "export",
+        format!("{} fursonas as {file_name}", entries.len()),
+    )?;
 
     let reply = poise::CreateReply::default()
+        .ephemeral(true)
         .content("Here is the exported fursonas file:")
-        .attachment(file_content);
+        .attachment(serenity::CreateAttachment::bytes(contents, file_name));
 
     ctx.send(reply).await?;
 
@@ -0,0 +1,282 @@
+use crate::{
+    catalog::{Rarity, TraitKind, TraitOption, MAX_OPTIONS},
+    render, Context, Error,
+};
+use poise::serenity_prelude as serenity;
+
+/// The parent command for managing this server's trait catalog.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands("add", "remove", "list", "odds", "reset")
+)]
+pub async fn catalog(_: Context<'_>) -> Result<(), Error> {
+    // This will never be called, because `subcommand_required` parameter is set
+    Ok(())
+}
+
+/// Add an option to one of this server's traits.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+#[allow(clippy::too_many_arguments)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "Trait to add the option to"]
+    #[rename = "trait"]
+    kind: TraitKind,
+    #[description = "Name shown to users"]
+    #[max_length = 100]
+    label: String,
+    #[description = "Stored value, defaults to the label in lowercase"]
+    #[max_length = 100]
+    value: Option<String>,
+    #[description = "Short description shown in the menu"]
+    #[max_length = 100]
+    description: Option<String>,
+    #[description = "Emoji shown next to the option"] emoji: Option<String>,
+    #[description = "How rare the option is when rolling random fursonas"] rarity: Option<Rarity>,
+    #[description = "Relative chance of being rolled, overriding the rarity's default"]
+    #[min = 0.01]
+    weight: Option<f64>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let value = value.unwrap_or_else(|| TraitOption::value_for(&label));
+
+    let option = TraitOption {
+        description,
+        emoji,
+        rarity: rarity.unwrap_or_default(),
+        weight,
+        ..TraitOption::new(label, value)
+    };
+
+    if let Some(problem) = option.problem() {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(format!("Couldn't add **{}**: {problem}", option.label)),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let response = ctx.data().catalogs.update(guild_id, |catalog| {
+        let set = catalog.get_mut(kind);
+
+        if set.find(&option.value).is_some() {
+            return Ok(format!(
+                "{} already has an option with the value `{}`",
+                kind.name(),
+                option.value
+            ));
+        }
+
+        if set.options.len() >= MAX_OPTIONS {
+            return Ok(format!(
+                "{} already has the maximum of {MAX_OPTIONS} options",
+                kind.name()
+            ));
+        }
+
+        let response = format!("Added **{}** to {}", option.label, kind.name());
+
+        set.options.push(option);
+
+        Ok(response)
+    })?;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(response),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Remove an option from one of this server's traits.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Trait to remove the option from"]
+    #[rename = "trait"]
+    kind: TraitKind,
+    #[description = "Value of the option to remove"]
+    #[autocomplete = "autocomplete_value"]
+    value: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let response = ctx.data().catalogs.update(guild_id, |catalog| {
+        let set = catalog.get(kind);
+
+        let Some(index) = set.options.iter().position(|o| o.value == value) else {
+            return Ok(format!("{} has no option `{value}`", kind.name()));
+        };
+
+        if set.options.len() == 1 {
+            return Ok(format!("{} must keep at least one option", kind.name()));
+        }
+
+        // Options whose rules point at a removed option could never be picked
+        let references = catalog.referenced_by(&value);
+
+        if !references.is_empty() {
+            return Ok(format!(
+                "`{value}` can't be removed while the rules of {} mention it",
+                render::join_list(
+                    &references
+                        .iter()
+                        .map(|r| format!("**{r}**"))
+                        .collect::<Vec<_>>()
+                )
+            ));
+        }
+
+        let option = catalog.get_mut(kind).options.remove(index);
+
+        Ok(format!("Removed **{}** from {}", option.label, kind.name()))
+    })?;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(response),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// List the options this server offers for each trait.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn list(
+    ctx: Context<'_>,
+    #[description = "Only list this trait"]
+    #[rename = "trait"]
+    kind: Option<TraitKind>,
+) -> Result<(), Error> {
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+
+    let kinds = match kind {
+        Some(kind) => vec![kind],
+        None => TraitKind::ALL.to_vec(),
+    };
+
+    let mut embed = serenity::CreateEmbed::new().title("Trait Catalog");
+
+    for kind in kinds {
+        let options = catalog
+            .get(kind)
+            .options
+            .iter()
+            .map(|o| match &o.emoji {
+                Some(emoji) => format!("{emoji} {} (`{}`)", o.label, o.value),
+                None => format!("{} (`{}`)", o.label, o.value),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        embed = embed.field(kind.name(), options, true);
+    }
+
+    ctx.send(poise::CreateReply::default().ephemeral(true).embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Show the chance of rolling each option in a random fursona.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn odds(
+    ctx: Context<'_>,
+    #[description = "Only show the odds of this trait"]
+    #[rename = "trait"]
+    kind: Option<TraitKind>,
+) -> Result<(), Error> {
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+
+    let kinds = match kind {
+        Some(kind) => vec![kind],
+        None => TraitKind::ALL.to_vec(),
+    };
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title("Random Fursona Odds")
+        .description("The chance of each option being picked in a single roll.");
+
+    for kind in kinds {
+        let odds = catalog
+            .get(kind)
+            .odds()
+            .into_iter()
+            .map(|(o, chance)| format!("{} {} - {:.1}%", o.rarity.emoji(), o.label, chance * 100.0))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let name = match catalog.picks(kind) {
+            picks if kind.multiple() => {
+                format!("{} ({} to {} picks)", kind.name(), picks.min, picks.max)
+            }
+            _ => kind.name().to_string(),
+        };
+
+        embed = embed.field(name, odds, true);
+    }
+
+    let legend = [
+        Rarity::Common,
+        Rarity::Uncommon,
+        Rarity::Rare,
+        Rarity::Legendary,
+    ]
+    .map(|r| format!("{} {}", r.emoji(), r.name()))
+    .join("  ");
+
+    embed = embed.footer(serenity::CreateEmbedFooter::new(legend));
+
+    ctx.send(poise::CreateReply::default().ephemeral(true).embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Reset this server's catalog back to the default options.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn reset(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.data().catalogs.reset(ctx.guild_id().unwrap())?;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content("The trait catalog has been reset to the defaults"),
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn autocomplete_value(ctx: Context<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+    let partial = partial.to_lowercase();
+
+    TraitKind::ALL
+        .iter()
+        .flat_map(|&kind| {
+            catalog
+                .get(kind)
+                .options
+                .iter()
+                .map(move |o| (kind, o.clone()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|(_, o)| o.value.contains(&partial) || o.label.to_lowercase().contains(&partial))
+        .map(|(kind, o)| {
+            serenity::AutocompleteChoice::new(format!("{}: {}", kind.name(), o.label), o.value)
+        })
+        .take(25)
+        .collect()
+}
@@ -0,0 +1,347 @@
+use super::{
+    apply_selections, author_profile, autocomplete_character, compatibility, moderation,
+    trait_step, wizard_values, DetailsModal,
+};
+use crate::{
+    catalog::{TraitCatalog, TraitKind},
+    fursona::{same_name, Fursona},
+    render,
+    wizard::{Outcome, Selections, Wizard},
+    Context, Error,
+};
+use poise::serenity_prelude as serenity;
+use std::{sync::atomic::Ordering, time::Duration};
+
+/// A part of a fursona that can be edited on its own.
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Trait(TraitKind),
+    Name,
+    Pronouns,
+    Bio,
+    Reference,
+}
+
+impl Field {
+    const ALL: [Field; 9] = [
+        Field::Name,
+        Field::Pronouns,
+        Field::Bio,
+        Field::Reference,
+        Field::Trait(TraitKind::Species),
+        Field::Trait(TraitKind::BodyType),
+        Field::Trait(TraitKind::Markings),
+        Field::Trait(TraitKind::Accessories),
+        Field::Trait(TraitKind::Personality),
+    ];
+
+    fn id(self) -> &'static str {
+        match self {
+            Field::Trait(kind) => kind.id(),
+            Field::Name => "name",
+            Field::Pronouns => "pronouns",
+            Field::Bio => "bio",
+            Field::Reference => "reference",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Trait(kind) => kind.name(),
+            Field::Name => "Name",
+            Field::Pronouns => "Pronouns",
+            Field::Bio => "Bio",
+            Field::Reference => "Reference link",
+        }
+    }
+
+    /// Copies the field's value from one version of a fursona to another.
+    fn copy(self, from: &Fursona, to: &mut Fursona) {
+        match self {
+            Field::Trait(kind) => {
+                to.set_values(
+                    kind,
+                    from.values(kind).into_iter().map(String::from).collect(),
+                );
+
+                if kind == TraitKind::Species {
+                    to.custom_species = from.custom_species.clone();
+                }
+            }
+            Field::Name => to.name = from.name.clone(),
+            Field::Pronouns => to.pronouns = from.pronouns.clone(),
+            Field::Bio => to.bio = from.bio.clone(),
+            Field::Reference => to.reference = from.reference.clone(),
+        }
+    }
+
+    fn parse(id: &str) -> Option<Field> {
+        Field::ALL.into_iter().find(|f| f.id() == id)
+    }
+
+    /// Returns the field's current value, formatted for display.
+    fn display(self, fursona: &Fursona, catalog: &TraitCatalog) -> String {
+        let value = match self {
+            Field::Trait(kind) => Some(render::trait_value(fursona, catalog, kind)),
+            Field::Name => Some(fursona.name.clone()),
+            Field::Pronouns => fursona.pronouns.clone(),
+            Field::Bio => fursona.bio.clone(),
+            Field::Reference => fursona.reference.clone(),
+        };
+
+        value
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "None".to_string())
+    }
+}
+
+/// A command to change a single part of one of your characters.
+#[poise::command(slash_command, rename = "edit")]
+pub async fn edit_fursona(
+    ctx: Context<'_>,
+    #[description = "What to change"]
+    #[autocomplete = "autocomplete_field"]
+    field: String,
+    #[description = "Character to change, defaults to your active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let Some(field) = Field::parse(&field) else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(format!("`{field}` isn't something that can be edited")),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let Some(before) = author_profile(ctx).await?.find(name.as_deref()).cloned() else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("You don't have that fursona!"),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+
+    let (edited, msg) = match field {
+        Field::Trait(kind) => match edit_trait(ctx, &catalog, &before, kind).await? {
+            Some(edited) => edited,
+            None => return Ok(()),
+        },
+        _ => match edit_text(ctx, &before, field).await? {
+            Some(after) => (after, None),
+            None => return Ok(()),
+        },
+    };
+
+    // Reload in case the profile changed while waiting, and only change the edited field
+    let mut profile = author_profile(ctx).await?;
+
+    let Some(before) = profile.get(&before.name).cloned() else {
+        let reply = poise::CreateReply::default()
+            .ephemeral(true)
+            .content("You don't have that fursona!")
+            .components(vec![]);
+
+        match msg {
+            Some(msg) => msg.edit(ctx, reply).await?,
+            None => {
+                ctx.send(reply).await?;
+            }
+        }
+
+        return Ok(());
+    };
+
+    let mut after = before.clone();
+    field.copy(&edited, &mut after);
+
+    let problem = DetailsModal::from_fursona(&after).validate().or_else(|| {
+        let renamed = !same_name(&after.name, &before.name);
+
+        (renamed && profile.get(&after.name).is_some())
+            .then(|| format!("You already have a fursona called **{}**!", after.name))
+    });
+
+    let content = match problem {
+        Some(problem) => problem,
+        None => {
+            let mut content = format!(
+                "Updated **{}** of **{}**\n```diff\n- {}\n+ {}\n```",
+                field.name(),
+                after.name,
+                field.display(&before, &catalog),
+                field.display(&after, &catalog),
+            );
+
+            if after.custom_species != before.custom_species {
+                if let Some(note) = moderation::submit_species(ctx, &after).await? {
+                    content.push_str(&format!("\n{note}"));
+                }
+            }
+
+            profile.replace(&before.name, after);
+            ctx.data().fursonas.put(ctx.author().id, profile).await?;
+
+            content
+        }
+    };
+
+    let reply = poise::CreateReply::default()
+        .ephemeral(true)
+        .content(content)
+        .components(vec![]);
+
+    match msg {
+        Some(msg) => msg.edit(ctx, reply).await?,
+        None => {
+            ctx.send(reply).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows a single select menu for a trait, returning the edited fursona and the message it was
+/// shown in, or `None` if the user cancelled.
+async fn edit_trait<'a>(
+    ctx: Context<'a>,
+    catalog: &TraitCatalog,
+    fursona: &Fursona,
+    kind: TraitKind,
+) -> Result<Option<(Fursona, Option<poise::ReplyHandle<'a>>)>, Error> {
+    let msg = ctx
+        .send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(format!("Editing {}...", kind.name())),
+        )
+        .await?;
+
+    let mut current = Selections::default();
+    current.set(kind.id(), wizard_values(fursona, kind));
+
+    let custom = ctx.guild_id().is_some();
+
+    let outcome = Wizard::new(vec![trait_step(catalog, kind, custom)])
+        .selections(current)
+        .without_review()
+        .submit_label("Save")
+        .validate(compatibility(catalog, fursona, &[kind]))
+        .run(ctx, &msg)
+        .await?;
+
+    let content = match outcome {
+        Outcome::Completed(selections) => {
+            let mut edited = fursona.clone();
+            apply_selections(catalog, &mut edited, &selections, &[kind]);
+
+            return Ok(Some((edited, Some(msg))));
+        }
+        Outcome::Cancelled => "Action cancelled",
+        Outcome::TimedOut => "Timed out",
+    };
+
+    msg.edit(
+        ctx,
+        poise::CreateReply::default()
+            .content(content)
+            .components(vec![]),
+    )
+    .await?;
+
+    Ok(None)
+}
+
+/// Shows a modal with a single text input, returning the edited fursona or `None` if the modal
+/// timed out.
+async fn edit_text(
+    ctx: Context<'_>,
+    fursona: &Fursona,
+    field: Field,
+) -> Result<Option<Fursona>, Error> {
+    let poise::Context::Application(app_ctx) = ctx else {
+        return Ok(None);
+    };
+
+    let (current, max_length, style) = match field {
+        Field::Name => (Some(&fursona.name), 32, serenity::InputTextStyle::Short),
+        Field::Pronouns => (
+            fursona.pronouns.as_ref(),
+            32,
+            serenity::InputTextStyle::Short,
+        ),
+        Field::Bio => (
+            fursona.bio.as_ref(),
+            300,
+            serenity::InputTextStyle::Paragraph,
+        ),
+        Field::Reference => (
+            fursona.reference.as_ref(),
+            200,
+            serenity::InputTextStyle::Short,
+        ),
+        Field::Trait(_) => return Ok(None),
+    };
+
+    let mut input = serenity::CreateInputText::new(style, field.name(), field.id())
+        .max_length(max_length)
+        .required(matches!(field, Field::Name));
+
+    if let Some(current) = current {
+        input = input.value(current);
+    }
+
+    let modal = serenity::CreateQuickModal::new(format!("Edit {}", field.name()))
+        .timeout(Duration::from_secs(300))
+        .field(input);
+
+    let Some(response) = app_ctx
+        .interaction
+        .quick_modal(ctx.serenity_context(), modal)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    // Close the modal, the reply is sent as a follow-up
+    response
+        .interaction
+        .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+        .await?;
+    app_ctx
+        .has_sent_initial_response
+        .store(true, Ordering::SeqCst);
+
+    let value = response.inputs.into_iter().next().unwrap_or_default();
+    let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+
+    let mut edited = fursona.clone();
+
+    match field {
+        Field::Name => edited.name = value.unwrap_or_default(),
+        Field::Pronouns => edited.pronouns = value,
+        Field::Bio => edited.bio = value,
+        Field::Reference => edited.reference = value,
+        Field::Trait(_) => {}
+    }
+
+    Ok(Some(edited))
+}
+
+async fn autocomplete_field(_: Context<'_>, partial: &str) -> Vec<serenity::AutocompleteChoice> {
+    let partial = partial.to_lowercase();
+
+    Field::ALL
+        .into_iter()
+        .filter(|f| f.name().to_lowercase().contains(&partial) || f.id().contains(&partial))
+        .map(|f| serenity::AutocompleteChoice::new(f.name(), f.id()))
+        .collect()
+}
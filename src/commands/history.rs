@@ -0,0 +1,234 @@
+use super::{author_profile, autocomplete_character};
+use crate::{fursona::same_name, render, Context, Error};
+use poise::serenity_prelude as serenity;
+
+/// How many versions are listed on each page of `/fursona history`.
+const PAGE_SIZE: usize = 5;
+
+/// A command to list the past versions of one of your characters.
+#[poise::command(slash_command, rename = "history")]
+pub async fn history_fursona(
+    ctx: Context<'_>,
+    #[description = "Character to show the history of, defaults to your active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let profile = author_profile(ctx).await?;
+
+    let Some(fursona) = profile.find(name.as_deref()) else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("You don't have that fursona!"),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let history = profile.history(&fursona.name);
+
+    if history.is_empty() {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(format!("**{}** hasn't been changed yet", fursona.name)),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let pages = history
+        .chunks(PAGE_SIZE)
+        .map(|revisions| {
+            let lines = revisions
+                .iter()
+                .map(|r| {
+                    format!(
+                        "**v{}** - <t:{}:f> - changed {}",
+                        r.version,
+                        r.saved_at.unix_timestamp(),
+                        render::join_list(&r.changes)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            format!(
+                "**History of {}**\nRestore a version with `/fursona restore`\n\n{lines}",
+                fursona.name
+            )
+        })
+        .collect::<Vec<_>>();
+
+    paginate(ctx, &pages).await
+}
+
+/// Shows pages of text to the user only, with buttons to move between them.
+async fn paginate(ctx: Context<'_>, pages: &[String]) -> Result<(), Error> {
+    let buttons = |page: usize| {
+        vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new("history_previous")
+                .emoji('◀')
+                .style(serenity::ButtonStyle::Secondary)
+                .disabled(page == 0),
+            serenity::CreateButton::new("history_next")
+                .emoji('▶')
+                .style(serenity::ButtonStyle::Secondary)
+                .disabled(page + 1 == pages.len()),
+        ])]
+    };
+
+    let mut page = 0;
+    let mut reply = poise::CreateReply::default()
+        .ephemeral(true)
+        .content(&pages[page]);
+
+    // A single page needs no buttons
+    if pages.len() > 1 {
+        reply = reply.components(buttons(page));
+    }
+
+    let msg = ctx.send(reply).await?;
+
+    if pages.len() == 1 {
+        return Ok(());
+    }
+
+    let message_id = msg.message().await?.id;
+
+    while let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .message_id(message_id)
+        .timeout(std::time::Duration::from_secs(120))
+        .await
+    {
+        match interaction.data.custom_id.as_str() {
+            "history_previous" => page = page.saturating_sub(1),
+            "history_next" => page = (page + 1).min(pages.len() - 1),
+            _ => continue,
+        }
+
+        let response = serenity::CreateInteractionResponseMessage::new()
+            .content(&pages[page])
+            .components(buttons(page));
+
+        interaction
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::UpdateMessage(response),
+            )
+            .await?;
+    }
+
+    // Remove the buttons once they time out
+    let reply = poise::CreateReply::default()
+        .content(&pages[page])
+        .components(vec![]);
+
+    msg.edit(ctx, reply).await?;
+
+    Ok(())
+}
+
+/// A command to undo the last change to one of your characters.
+#[poise::command(slash_command, rename = "undo")]
+pub async fn undo_fursona(
+    ctx: Context<'_>,
+    #[description = "Character to undo the last change of, defaults to your active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    restore(ctx, name, None).await
+}
+
+/// A command to bring back a past version of one of your characters.
+#[poise::command(slash_command, rename = "restore")]
+pub async fn restore_fursona(
+    ctx: Context<'_>,
+    #[description = "Version to restore, as listed by `/fursona history`"]
+    #[min = 1]
+    version: u32,
+    #[description = "Character to restore, defaults to your active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    restore(ctx, name, Some(version)).await
+}
+
+async fn restore(
+    ctx: Context<'_>,
+    name: Option<String>,
+    version: Option<u32>,
+) -> Result<(), Error> {
+    let mut profile = author_profile(ctx).await?;
+
+    let Some(current) = profile.find(name.as_deref()).cloned() else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("You don't have that fursona!"),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let Some(revision) = profile.revision(&current.name, version) else {
+        let content = match version {
+            Some(version) => format!("**{}** has no version {version}", current.name),
+            None => format!("**{}** has no changes to undo", current.name),
+        };
+
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(content),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    // Restoring an old name must not clash with another character
+    let restored_name = &revision.fursona.name;
+
+    if !same_name(restored_name, &current.name) && profile.get(restored_name).is_some() {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content(format!(
+                    "That version is called **{restored_name}**, but you already have another fursona with that name!"
+                )),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let content = match version {
+        Some(version) => format!("Restored version {version} of **{}**", current.name),
+        None => format!(
+            "Undid the change to {} of **{}**",
+            render::join_list(&revision.changes),
+            current.name
+        ),
+    };
+
+    let restored = profile.restore(&current.name, version).unwrap();
+
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+    let embed = render::fursona_embed(&restored, ctx.author(), &catalog);
+
+    ctx.data().fursonas.put(ctx.author().id, profile).await?;
+
+    let reply = poise::CreateReply::default()
+        .ephemeral(true)
+        .content(content)
+        .embed(embed);
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
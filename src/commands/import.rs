@@ -0,0 +1,196 @@
+use super::confirm;
+use crate::{
+    export::Format,
+    import::{self, Invalid, Mode, Report},
+    render, Context, Error,
+};
+use poise::serenity_prelude as serenity;
+use std::collections::{HashMap, HashSet};
+
+/// How many invalid rows the report lists before summarising the rest.
+const INVALID_LIMIT: usize = 10;
+
+/// How much of each invalid row's reason is shown, as reasons can quote the file.
+const REASON_LIMIT: usize = 100;
+
+/// The longest the report can be, leaving room in Discord's 2000 character limit for the
+/// question asked after it.
+const REPORT_LIMIT: usize = 1600;
+
+/// How much of the file name is shown.
+const FILE_NAME_LIMIT: usize = 100;
+
+/// Import fursonas from an export file, after showing what would change.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "import"
+)]
+pub async fn import_fursonas(
+    ctx: Context<'_>,
+    #[description = "A JSON or CSV file from `/export_fursonas`"] file: serenity::Attachment,
+    #[description = "Merge with existing characters, or replace the ones this server imported, defaults to merge"]
+    mode: Option<Mode>,
+) -> Result<(), Error> {
+    let mode = mode.unwrap_or_default();
+    let file_name = render::truncate(&file.filename, FILE_NAME_LIMIT);
+
+    let Some(format @ (Format::Json | Format::Csv)) = Format::from_file_name(&file.filename) else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("Only JSON and CSV exports can be imported"),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    ctx.defer_ephemeral().await?;
+
+    let contents = file.download().await?;
+    let catalog = ctx.data().catalogs.get(ctx.guild_id());
+
+    let (records, invalid) = match import::parse(format, &contents, &catalog) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            ctx.say(render::truncate(
+                &format!("Couldn't read **{file_name}**: {e}"),
+                REPORT_LIMIT,
+            ))
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let report = plan(ctx, mode, records.clone(), invalid.clone()).await?;
+
+    if report.new + report.overwritten == 0 {
+        ctx.say(format!(
+            "{}\nThere is nothing to import.",
+            describe(&report, mode)
+        ))
+        .await?;
+
+        return Ok(());
+    }
+
+    let question = format!(
+        "{}\nDo you want to import **{}**?",
+        describe(&report, mode),
+        file_name
+    );
+
+    let Some(msg) = confirm(ctx, question, "Import").await? else {
+        return Ok(());
+    };
+
+    // Plan again in case users changed their characters while waiting
+    let report = plan(ctx, mode, records, invalid).await?;
+
+    for (user_id, profile) in report.profiles {
+        ctx.data().fursonas.put(user_id, profile).await?;
+    }
+
+    ctx.data().audit.record(
+        ctx.guild_id(),
+        ctx.author().id,
+        "import",
+        format!(
+            "{} new and {} overwritten fursonas from {} ({mode:?})",
+            report.new, report.overwritten, file.filename
+        ),
+    )?;
+
+    msg.edit(
+        ctx,
+        poise::CreateReply::default()
+            .content(format!(
+                "Imported {} new and {} overwritten fursonas",
+                report.new, report.overwritten
+            ))
+            .components(vec![]),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Loads the profiles of everyone in the file and works out what importing would do.
+async fn plan(
+    ctx: Context<'_>,
+    mode: Mode,
+    records: Vec<import::Record>,
+    invalid: Vec<Invalid>,
+) -> Result<Report, Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let user_ids = records.iter().map(|r| r.user_id).collect::<HashSet<_>>();
+    let mut existing = HashMap::new();
+
+    for user_id in user_ids {
+        // Only members of this server can be given characters from it
+        if guild_id.member(ctx, user_id).await.is_err() {
+            continue;
+        }
+
+        let profile = ctx.data().fursonas.get(user_id).await?;
+
+        existing.insert(user_id, profile.unwrap_or_default());
+    }
+
+    let (records, outsiders) = records
+        .into_iter()
+        .partition::<Vec<_>, _>(|r| existing.contains_key(&r.user_id));
+
+    let mut report = import::plan(guild_id, mode, records, existing, ctx.data().max_characters);
+
+    report.invalid.extend(invalid);
+    report
+        .invalid
+        .extend(outsiders.into_iter().map(|r| Invalid {
+            row: r.row,
+            reason: format!("<@{}> isn't a member of this server", r.user_id),
+        }));
+    report.invalid.sort_by_key(|i| i.row);
+
+    Ok(report)
+}
+
+/// Summarises a dry run of the import.
+fn describe(report: &Report, mode: Mode) -> String {
+    let mut lines = vec![
+        format!("**New:** {}", report.new),
+        format!("**Overwritten:** {}", report.overwritten),
+    ];
+
+    if mode == Mode::Replace {
+        lines.push(format!("**Removed:** {}", report.removed));
+
+        lines.push(
+            "Only characters this server imported before are removed, and their history is lost. \
+             Characters users made themselves or imported elsewhere are kept."
+                .to_string(),
+        );
+    }
+
+    lines.push(format!("**Invalid:** {}", report.invalid.len()));
+
+    for invalid in report.invalid.iter().take(INVALID_LIMIT) {
+        lines.push(format!(
+            "- Row {}: {}",
+            invalid.row,
+            render::truncate(&invalid.reason, REASON_LIMIT)
+        ));
+    }
+
+    if report.invalid.len() > INVALID_LIMIT {
+        lines.push(format!(
+            "- and {} more",
+            report.invalid.len() - INVALID_LIMIT
+        ));
+    }
+
+    render::truncate(&lines.join("\n"), REPORT_LIMIT)
+}
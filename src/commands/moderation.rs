@@ -0,0 +1,210 @@
+use crate::{
+    fursona::Fursona,
+    moderation::{review_buttons, review_embed},
+    settings::ExportPermission,
+    Context, Error,
+};
+use poise::serenity_prelude as serenity;
+
+/// How many pending requests `/moderation queue` shows at once.
+const QUEUE_LIMIT: usize = 10;
+
+/// The parent command for reviewing what members submit.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands("channel", "queue", "export_permission", "export_role")
+)]
+pub async fn moderation(_: Context<'_>) -> Result<(), Error> {
+    // This will never be called, because `subcommand_required` parameter is set
+    Ok(())
+}
+
+/// Set the channel species requests are posted in for review.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn channel(
+    ctx: Context<'_>,
+    #[description = "Channel to post requests in, leave empty to stop posting them"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let channel_id = channel.as_ref().map(|c| c.id);
+
+    ctx.data()
+        .settings
+        .update(guild_id, |settings| settings.mod_channel = channel_id)?;
+
+    let content = match channel_id {
+        Some(channel_id) => format!("Species requests will be posted in <#{channel_id}>"),
+        None => "Species requests will no longer be posted, review them with `/moderation queue`"
+            .to_string(),
+    };
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(content),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Review the species requests waiting in this server.
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+pub async fn queue(ctx: Context<'_>) -> Result<(), Error> {
+    let pending = ctx.data().species_queue.pending(ctx.guild_id().unwrap());
+
+    if pending.is_empty() {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("There are no species requests waiting for review"),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(format!(
+                "{} species requests are waiting for review{}",
+                pending.len(),
+                if pending.len() > QUEUE_LIMIT {
+                    format!(", showing the oldest {QUEUE_LIMIT}")
+                } else {
+                    String::new()
+                }
+            )),
+    )
+    .await?;
+
+    for submission in pending.iter().take(QUEUE_LIMIT) {
+        let reply = poise::CreateReply::default()
+            .ephemeral(true)
+            .embed(review_embed(submission))
+            .components(review_buttons(submission));
+
+        ctx.send(reply).await?;
+    }
+
+    Ok(())
+}
+
+/// Set the permission members need to export everyone's fursonas.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "export-permission"
+)]
+pub async fn export_permission(
+    ctx: Context<'_>,
+    #[description = "Permission needed to export"] permission: ExportPermission,
+) -> Result<(), Error> {
+    ctx.data()
+        .settings
+        .update(ctx.guild_id().unwrap(), |settings| {
+            settings.export_permission = permission
+        })?;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(format!(
+                "Members with the {} permission can now export fursonas",
+                permission.name()
+            )),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Allow or stop allowing a role to export everyone's fursonas.
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    rename = "export-role"
+)]
+pub async fn export_role(
+    ctx: Context<'_>,
+    #[description = "Role to allow, or stop allowing if it already is"] role: serenity::Role,
+) -> Result<(), Error> {
+    let mut allowed = false;
+
+    ctx.data()
+        .settings
+        .update(ctx.guild_id().unwrap(), |settings| {
+            let roles = &mut settings.export_roles;
+
+            if let Some(index) = roles.iter().position(|r| *r == role.id) {
+                roles.remove(index);
+            } else {
+                roles.push(role.id);
+                allowed = true;
+            }
+        })?;
+
+    let content = if allowed {
+        format!("<@&{}> can now export fursonas", role.id)
+    } else {
+        format!("<@&{}> can no longer export fursonas", role.id)
+    };
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(content),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Queues the custom species of a newly saved fursona for review, posting it in the server's mod
+/// channel. Returns a note telling the user it is pending.
+pub async fn submit_species(ctx: Context<'_>, fursona: &Fursona) -> Result<Option<String>, Error> {
+    let (Some(guild_id), Some(custom)) = (ctx.guild_id(), &fursona.custom_species) else {
+        return Ok(None);
+    };
+
+    if custom.rejected.is_some() {
+        return Ok(None);
+    }
+
+    let note = format!(
+        "**{}** isn't in the species list yet, so it will show as pending until a moderator approves it.",
+        custom.label
+    );
+
+    let Some(submission) = ctx.data().species_queue.push(
+        guild_id,
+        ctx.author().id,
+        fursona.name.clone(),
+        custom.label.clone(),
+        custom.value.clone(),
+    )?
+    else {
+        // Already waiting for review
+        return Ok(Some(note));
+    };
+
+    if let Some(channel) = ctx.data().settings.get(guild_id).mod_channel {
+        let message = serenity::CreateMessage::new()
+            .embed(review_embed(&submission))
+            .components(review_buttons(&submission));
+
+        // Requests can still be reviewed from the queue if the channel can't be posted in
+        if let Err(e) = channel.send_message(ctx, message).await {
+            println!("Couldn't post species request in {}: {}", channel, e);
+        }
+    }
+
+    Ok(Some(note))
+}
@@ -0,0 +1,259 @@
+use super::{author_profile, autocomplete_character, cancel_action};
+use crate::{
+    card,
+    fursona::Fursona,
+    palette::{self, Palette},
+    render, Context, Error,
+};
+use poise::serenity_prelude as serenity;
+use std::time::Duration;
+
+const PRESET_ID: &str = "palette_preset";
+const CUSTOM_ID: &str = "palette_custom";
+const RANDOM_ID: &str = "palette_random";
+const CLEAR_ID: &str = "palette_clear";
+const CANCEL_ID: &str = "palette_cancel";
+
+/// The colours of a palette typed in as hex codes.
+#[derive(Debug, poise::Modal)]
+#[name = "Fursona palette"]
+struct PaletteModal {
+    #[name = "Base colour"]
+    #[placeholder = "e.g. #C8553D"]
+    #[min_length = 3]
+    #[max_length = 7]
+    base: String,
+    #[name = "Secondary colour"]
+    #[placeholder = "e.g. #F4EDE4"]
+    #[min_length = 3]
+    #[max_length = 7]
+    secondary: String,
+    #[name = "Eye colour"]
+    #[placeholder = "e.g. #E0A526"]
+    #[min_length = 3]
+    #[max_length = 7]
+    eyes: String,
+}
+
+impl PaletteModal {
+    fn from_palette(palette: &Palette) -> Self {
+        Self {
+            base: palette::to_hex(palette.base),
+            secondary: palette::to_hex(palette.secondary),
+            eyes: palette::to_hex(palette.eyes),
+        }
+    }
+
+    /// Parses the hex codes, or returns a message for the user naming the invalid ones.
+    fn parse(&self) -> Result<Palette, String> {
+        let colours = [
+            ("Base colour", &self.base),
+            ("Secondary colour", &self.secondary),
+            ("Eye colour", &self.eyes),
+        ];
+
+        let invalid = colours
+            .iter()
+            .filter(|(_, hex)| palette::parse_hex(hex).is_none())
+            .map(|(name, hex)| format!("{name} `{}`", hex.trim()))
+            .collect::<Vec<_>>();
+
+        if !invalid.is_empty() {
+            return Err(format!(
+                "{} isn't a valid hex colour, use something like `#C8553D`",
+                render::join_list(&invalid)
+            ));
+        }
+
+        Ok(Palette {
+            base: palette::parse_hex(&self.base).unwrap(),
+            secondary: palette::parse_hex(&self.secondary).unwrap(),
+            eyes: palette::parse_hex(&self.eyes).unwrap(),
+        })
+    }
+}
+
+/// A command to pick the colours of one of your characters.
+#[poise::command(slash_command, rename = "palette")]
+pub async fn palette_fursona(
+    ctx: Context<'_>,
+    #[description = "Character to colour, defaults to your active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let Some(before) = author_profile(ctx).await?.find(name.as_deref()).cloned() else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("You don't have that fursona!"),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let msg = ctx.send(palette_menu(&before, None)).await?;
+    let message_id = msg.message().await?.id;
+
+    while let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .message_id(message_id)
+        .timeout(Duration::from_secs(300))
+        .await
+    {
+        let palette = match interaction.data.custom_id.as_str() {
+            PRESET_ID => {
+                interaction.defer(ctx).await?;
+
+                match &interaction.data.kind {
+                    serenity::ComponentInteractionDataKind::StringSelect { values } => values
+                        .first()
+                        .and_then(|id| palette::preset(id))
+                        .map(|preset| Some(preset.palette)),
+                    _ => None,
+                }
+            }
+            CUSTOM_ID => {
+                // The modal is the response to the button, so it can't be deferred
+                let modal = poise::execute_modal_on_component_interaction(
+                    ctx,
+                    interaction,
+                    before.palette.as_ref().map(PaletteModal::from_palette),
+                    Some(Duration::from_secs(300)),
+                )
+                .await?;
+
+                let Some(modal) = modal else {
+                    continue;
+                };
+
+                match modal.parse() {
+                    Ok(palette) => Some(Some(palette)),
+                    Err(problem) => {
+                        msg.edit(ctx, palette_menu(&before, Some(&problem))).await?;
+
+                        continue;
+                    }
+                }
+            }
+            RANDOM_ID => {
+                interaction.defer(ctx).await?;
+
+                let palette = palette::random(&mut rand::thread_rng());
+
+                Some(Some(palette))
+            }
+            CLEAR_ID => {
+                interaction.defer(ctx).await?;
+
+                Some(None)
+            }
+            CANCEL_ID => {
+                interaction.defer(ctx).await?;
+
+                cancel_action(ctx, msg).await?;
+
+                return Ok(());
+            }
+            _ => None,
+        };
+
+        let Some(palette) = palette else {
+            continue;
+        };
+
+        // Reload in case the profile changed while waiting
+        let mut profile = author_profile(ctx).await?;
+
+        let Some(mut after) = profile.get(&before.name).cloned() else {
+            let reply = poise::CreateReply::default()
+                .content("You don't have that fursona!")
+                .components(vec![]);
+
+            msg.edit(ctx, reply).await?;
+
+            return Ok(());
+        };
+
+        after.palette = palette;
+
+        let catalog = ctx.data().catalogs.get(ctx.guild_id());
+        let mut embed = render::fursona_embed(&after, ctx.author(), &catalog);
+        let mut reply = poise::CreateReply::default()
+            .ephemeral(true)
+            .content(format!("Updated the palette of **{}**", after.name))
+            .components(vec![]);
+
+        if let Some(palette) = &after.palette {
+            let png = card::render_swatches(palette)?;
+
+            embed = embed.image("attachment://palette.png");
+            reply = reply.attachment(serenity::CreateAttachment::bytes(png, "palette.png"));
+        }
+
+        profile.replace(&before.name, after);
+        ctx.data().fursonas.put(ctx.author().id, profile).await?;
+
+        msg.edit(ctx, reply.embed(embed)).await?;
+
+        return Ok(());
+    }
+
+    let reply = poise::CreateReply::default()
+        .content("Timed out")
+        .components(vec![]);
+
+    msg.edit(ctx, reply).await?;
+
+    Ok(())
+}
+
+/// Shows a character's palette with a preset menu and buttons to type, roll or clear one.
+fn palette_menu(fursona: &Fursona, warning: Option<&str>) -> poise::CreateReply {
+    let mut content = match &fursona.palette {
+        Some(palette) => format!("**Palette of {}**\n{}", fursona.name, palette.describe()),
+        None => format!("**{}** doesn't have a palette yet", fursona.name),
+    };
+
+    content.push_str("\nPick a preset, type in your own hex colours or roll a random palette.");
+
+    if let Some(warning) = warning {
+        content.push_str(&format!("\n:warning: {warning}"));
+    }
+
+    let options = palette::PRESETS
+        .iter()
+        .map(|preset| {
+            serenity::CreateSelectMenuOption::new(preset.name, preset.id)
+                .description(preset.palette.describe().replace('`', ""))
+        })
+        .collect();
+
+    let menu = serenity::CreateSelectMenu::new(
+        PRESET_ID,
+        serenity::CreateSelectMenuKind::String { options },
+    )
+    .placeholder("Choose a preset");
+
+    poise::CreateReply::default()
+        .ephemeral(true)
+        .content(content)
+        .components(vec![
+            serenity::CreateActionRow::SelectMenu(menu),
+            serenity::CreateActionRow::Buttons(vec![
+                serenity::CreateButton::new(CUSTOM_ID)
+                    .label("Hex Colours")
+                    .style(serenity::ButtonStyle::Primary),
+                serenity::CreateButton::new(RANDOM_ID)
+                    .label("Random")
+                    .style(serenity::ButtonStyle::Secondary),
+                serenity::CreateButton::new(CLEAR_ID)
+                    .label("Clear")
+                    .style(serenity::ButtonStyle::Secondary)
+                    .disabled(fursona.palette.is_none()),
+                serenity::CreateButton::new(CANCEL_ID)
+                    .label("Cancel")
+                    .style(serenity::ButtonStyle::Danger),
+            ]),
+        ])
+}
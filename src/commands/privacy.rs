@@ -0,0 +1,164 @@
+use super::{author_profile, autocomplete_character, confirm};
+use crate::{
+    audit::AuditEntry,
+    fursona::{same_name, Profile, Visibility},
+    moderation::Submission,
+    Context, Error,
+};
+use poise::serenity_prelude as serenity;
+
+/// Everything the bot holds about a user, as sent by `/privacy my-data`.
+#[derive(serde::Serialize)]
+struct UserData {
+    user_id: serenity::UserId,
+    /// Characters, their history and which one is active.
+    profile: Option<Profile>,
+    /// Custom species waiting for moderator review.
+    species_requests: Vec<Submission>,
+    /// Exports and imports the user ran as a server admin.
+    audit_log: Vec<AuditEntry>,
+}
+
+/// The parent command for managing the data the bot holds about you.
+#[poise::command(slash_command, subcommands("forget_me", "my_data", "exports"))]
+pub async fn privacy(_: Context<'_>) -> Result<(), Error> {
+    // This will never be called, because `subcommand_required` parameter is set
+    Ok(())
+}
+
+/// Permanently erase everything the bot holds about you.
+#[poise::command(slash_command, rename = "forget-me")]
+pub async fn forget_me(ctx: Context<'_>) -> Result<(), Error> {
+    let question = "This will permanently erase all of your characters, their history and any \
+        species requests. This can't be undone, are you sure?\n\nRecords of exports and imports \
+        you ran as a server admin are kept, so servers can see who accessed their members' data."
+        .to_string();
+
+    let Some(msg) = confirm(ctx, question, "Erase My Data").await? else {
+        return Ok(());
+    };
+
+    let user_id = ctx.author().id;
+
+    let profile = ctx.data().fursonas.delete(user_id).await?;
+    let requests = ctx.data().species_queue.forget(user_id)?;
+
+    let characters = profile.map_or(0, |p| p.characters.len());
+
+    let content = format!(
+        "Your data has been erased: {characters} characters and {requests} species requests were deleted."
+    );
+
+    msg.edit(
+        ctx,
+        poise::CreateReply::default()
+            .content(content)
+            .components(vec![]),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Get a copy of everything the bot holds about you.
+#[poise::command(slash_command, rename = "my-data")]
+pub async fn my_data(ctx: Context<'_>) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+
+    let data = UserData {
+        user_id,
+        profile: ctx.data().fursonas.get(user_id).await?,
+        species_requests: ctx.data().species_queue.by_user(user_id),
+        audit_log: ctx.data().audit.by_user(user_id)?,
+    };
+
+    let json = serde_json::to_vec_pretty(&data)?;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content("Here is everything held about you.")
+            .attachment(serenity::CreateAttachment::bytes(json, "my-data.json")),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Choose whether your characters are included when admins export the server's fursonas.
+#[poise::command(slash_command)]
+pub async fn exports(
+    ctx: Context<'_>,
+    #[description = "Whether to include your characters in exports"] include: bool,
+) -> Result<(), Error> {
+    let mut profile = author_profile(ctx).await?;
+    profile.exclude_from_exports = !include;
+
+    ctx.data().fursonas.put(ctx.author().id, profile).await?;
+
+    let content = if include {
+        "Your public characters will be included in exports"
+    } else {
+        "Your characters will be left out of exports"
+    };
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(content),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// A command to choose who can see one of your characters.
+#[poise::command(slash_command, rename = "visibility")]
+pub async fn visibility_fursona(
+    ctx: Context<'_>,
+    #[description = "Who can see the character"] visibility: Visibility,
+    #[description = "Character to change, defaults to your active one"]
+    #[autocomplete = "autocomplete_character"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let mut profile = author_profile(ctx).await?;
+
+    let Some(name) = profile.find(name.as_deref()).map(|f| f.name.clone()) else {
+        ctx.send(
+            poise::CreateReply::default()
+                .ephemeral(true)
+                .content("You don't have that fursona!"),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    // Changed in place, as visibility isn't kept in the character's history
+    if let Some(fursona) = profile
+        .characters
+        .iter_mut()
+        .find(|f| same_name(&f.name, &name))
+    {
+        fursona.visibility = visibility;
+    }
+
+    ctx.data().fursonas.put(ctx.author().id, profile).await?;
+
+    let content = match visibility {
+        Visibility::Public => format!("**{name}** can now be seen by anyone"),
+        Visibility::Server => {
+            format!("**{name}** can now only be seen in servers you are in, and won't be exported")
+        }
+        Visibility::Private => format!("**{name}** is now private, only you can see it"),
+    };
+
+    ctx.send(
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(format!("{} {content}", visibility.emoji())),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use crate::{
+    catalog::{TraitCatalog, TraitKind},
+    fursona::Fursona,
+    palette::{self, Palette},
+    Error,
+};
+use poise::serenity_prelude as serenity;
+
+/// The file formats fursonas can be exported as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Format {
+    #[default]
+    #[name = "JSON"]
+    Json,
+    #[name = "CSV"]
+    Csv,
+    #[name = "YAML"]
+    Yaml,
+    #[name = "Markdown"]
+    Markdown,
+}
+
+impl Format {
+    /// Picks the format of a file from its extension.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+
+        match extension.to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "md" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Yaml => "yaml",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+/// A fursona along with its owner, as written to JSON and YAML exports and read back by imports.
+///
+/// Every key is written, as null when unset, so each record has the same shape. How a character
+/// is shared and any custom species waiting for review are left out.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExportedFursona {
+    pub user_id: serenity::UserId,
+    pub username: String,
+    pub name: String,
+    pub species: String,
+    pub hybrid_with: Option<String>,
+    pub body_type: String,
+    pub markings: String,
+    pub accessories: Vec<String>,
+    pub personality: String,
+    pub pronouns: Option<String>,
+    pub bio: Option<String>,
+    pub reference: Option<String>,
+    /// Each colour as a hex code.
+    pub palette: Option<Palette>,
+    pub created_at: serenity::Timestamp,
+}
+
+impl ExportedFursona {
+    pub fn new(user_id: serenity::UserId, username: String, fursona: &Fursona) -> Self {
+        Self {
+            user_id,
+            username,
+            name: fursona.name.clone(),
+            species: fursona.species.clone(),
+            hybrid_with: fursona.hybrid_with.clone(),
+            body_type: fursona.body_type.clone(),
+            markings: fursona.markings.clone(),
+            accessories: fursona.accessories.clone(),
+            personality: fursona.personality.clone(),
+            pronouns: fursona.pronouns.clone(),
+            bio: fursona.bio.clone(),
+            reference: fursona.reference.clone(),
+            palette: fursona.palette,
+            created_at: fursona.created_at,
+        }
+    }
+
+    /// Turns the record back into a fursona, with the default visibility.
+    pub fn to_fursona(&self) -> Fursona {
+        let mut fursona = Fursona::new(
+            self.name.clone(),
+            self.species.clone(),
+            self.body_type.clone(),
+            self.markings.clone(),
+            self.accessories.clone(),
+            self.personality.clone(),
+        );
+
+        fursona.hybrid_with = self.hybrid_with.clone();
+        fursona.pronouns = self.pronouns.clone();
+        fursona.bio = self.bio.clone();
+        fursona.reference = self.reference.clone();
+        fursona.palette = self.palette;
+        fursona.created_at = self.created_at;
+
+        fursona
+    }
+}
+
+/// The columns of CSV and Markdown exports.
+pub const COLUMNS: [&str; 13] = [
+    "User ID",
+    "Username",
+    "Name",
+    "Species",
+    "Body Type",
+    "Markings",
+    "Accessories",
+    "Personality",
+    "Pronouns",
+    "Bio",
+    "Reference",
+    "Palette",
+    "Created",
+];
+
+/// Separates the labels of traits with more than one value, such as accessories.
+pub const LIST_SEPARATOR: &str = "; ";
+
+/// Characters that make spreadsheets read a cell as a formula.
+const FORMULA_STARTS: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Stops a CSV cell being run as a formula when opened in a spreadsheet, by starting it with a
+/// `'`. Cells already starting with one get another, so [`unescape_cell`] can tell them apart.
+fn escape_cell(cell: &str) -> String {
+    if cell.starts_with(FORMULA_STARTS) || cell.starts_with('\'') {
+        format!("'{cell}")
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Removes the `'` added by [`escape_cell`].
+pub fn unescape_cell(cell: &str) -> &str {
+    match cell.strip_prefix('\'') {
+        Some(rest) if rest.starts_with(FORMULA_STARTS) || rest.starts_with('\'') => rest,
+        _ => cell,
+    }
+}
+
+/// A fursona flattened into one row for spreadsheets, with catalog labels instead of values.
+#[derive(Debug)]
+struct Row {
+    user_id: String,
+    username: String,
+    name: String,
+    species: String,
+    body_type: String,
+    markings: String,
+    /// Every accessory, separated by `LIST_SEPARATOR`.
+    accessories: String,
+    personality: String,
+    pronouns: String,
+    bio: String,
+    reference: String,
+    palette: String,
+    created_at: String,
+}
+
+impl Row {
+    fn new(entry: &ExportedFursona, catalog: &TraitCatalog) -> Self {
+        let fursona = entry.to_fursona();
+        let labels = |kind| {
+            fursona
+                .values(kind)
+                .iter()
+                .map(|v| catalog.get(kind).label(v).to_string())
+                .collect::<Vec<_>>()
+                .join(LIST_SEPARATOR)
+        };
+
+        let palette = fursona.palette.map(|p| {
+            p.colours()
+                .iter()
+                .map(|(_, colour)| palette::to_hex(*colour))
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+
+        Self {
+            user_id: entry.user_id.to_string(),
+            username: entry.username.clone(),
+            name: entry.name.clone(),
+            // Both species of a hybrid are listed rather than its name, so it can be imported
+            species: labels(TraitKind::Species),
+            body_type: labels(TraitKind::BodyType),
+            markings: labels(TraitKind::Markings),
+            accessories: labels(TraitKind::Accessories),
+            personality: labels(TraitKind::Personality),
+            pronouns: fursona.pronouns.clone().unwrap_or_default(),
+            bio: fursona.bio.clone().unwrap_or_default(),
+            reference: fursona.reference.clone().unwrap_or_default(),
+            palette: palette.unwrap_or_default(),
+            created_at: fursona.created_at.to_string(),
+        }
+    }
+
+    fn cells(&self) -> [&str; 13] {
+        [
+            &self.user_id,
+            &self.username,
+            &self.name,
+            &self.species,
+            &self.body_type,
+            &self.markings,
+            &self.accessories,
+            &self.personality,
+            &self.pronouns,
+            &self.bio,
+            &self.reference,
+            &self.palette,
+            &self.created_at,
+        ]
+    }
+}
+
+/// Serializes fursonas in the given format. The catalog is used for labels in the flat formats.
+pub fn export(
+    format: Format,
+    entries: &[ExportedFursona],
+    catalog: &TraitCatalog,
+) -> Result<Vec<u8>, Error> {
+    match format {
+        Format::Json => Ok(serde_json::to_vec_pretty(entries)?),
+        Format::Yaml => Ok(serde_yaml_ng::to_string(entries)?.into_bytes()),
+        Format::Csv => to_csv(entries, catalog),
+        Format::Markdown => Ok(to_markdown(entries, catalog).into_bytes()),
+    }
+}
+
+fn to_csv(entries: &[ExportedFursona], catalog: &TraitCatalog) -> Result<Vec<u8>, Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record(COLUMNS)?;
+
+    for entry in entries {
+        let row = Row::new(entry, catalog);
+
+        writer.write_record(row.cells().map(escape_cell))?;
+    }
+
+    Ok(writer.into_inner().map_err(|e| e.into_error())?)
+}
+
+fn to_markdown(entries: &[ExportedFursona], catalog: &TraitCatalog) -> String {
+    let mut table = vec![
+        markdown_row(&COLUMNS),
+        markdown_row(&COLUMNS.map(|_| "---")),
+    ];
+
+    for entry in entries {
+        table.push(markdown_row(&Row::new(entry, catalog).cells()));
+    }
+
+    table.join("\n") + "\n"
+}
+
+fn markdown_row(cells: &[&str]) -> String {
+    let cells = cells
+        .iter()
+        .map(|cell| {
+            // Pipes would end the cell and newlines the row
+            cell.replace('|', "\\|")
+                .replace("\r\n", "<br>")
+                .replace('\n', "<br>")
+        })
+        .collect::<Vec<_>>();
+
+    format!("| {} |", cells.join(" | "))
+}
@@ -1,14 +1,46 @@
+use crate::{catalog::TraitKind, palette::Palette};
+use poise::serenity_prelude as serenity;
+use std::collections::HashMap;
+
+/// How many past versions are kept for each character.
+pub const HISTORY_LIMIT: usize = 20;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Fursona {
+    #[serde(default)]
+    pub name: String,
     pub species: String,
+    /// The second species of a hybrid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hybrid_with: Option<String>,
+    /// A species typed in by the user rather than picked from the catalog.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_species: Option<CustomSpecies>,
     pub body_type: String,
     pub markings: String,
     pub accessories: Vec<String>,
     pub personality: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pronouns: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    /// Link to a reference sheet or artwork.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Palette>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// The servers that imported this character, which can remove it by importing again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imported_into: Vec<serenity::GuildId>,
+    #[serde(default = "serenity::Timestamp::now")]
+    pub created_at: serenity::Timestamp,
 }
 
 impl Fursona {
     pub fn new(
+        name: String,
         species: String,
         body_type: String,
         markings: String,
@@ -16,11 +48,366 @@ impl Fursona {
         personality: String,
     ) -> Self {
         Self {
+            name,
             species,
+            hybrid_with: None,
+            custom_species: None,
             body_type,
             markings,
             accessories,
             personality,
+            pronouns: None,
+            bio: None,
+            reference: None,
+            palette: None,
+            visibility: Visibility::default(),
+            imported_into: Vec::new(),
+            created_at: serenity::Timestamp::now(),
+        }
+    }
+
+    /// Returns the values picked for a trait.
+    pub fn values(&self, kind: TraitKind) -> Vec<&str> {
+        match kind {
+            TraitKind::Species => [Some(&self.species), self.hybrid_with.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect(),
+            TraitKind::BodyType => vec![&self.body_type],
+            TraitKind::Markings => vec![&self.markings],
+            TraitKind::Accessories => self.accessories.iter().map(String::as_str).collect(),
+            TraitKind::Personality => vec![&self.personality],
+        }
+    }
+
+    /// Returns the names of the fields that differ between two versions of a fursona.
+    pub fn changes(&self, other: &Fursona) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        if self.name != other.name {
+            changes.push("Name".to_string());
+        }
+
+        if self.pronouns != other.pronouns {
+            changes.push("Pronouns".to_string());
+        }
+
+        if self.bio != other.bio {
+            changes.push("Bio".to_string());
+        }
+
+        if self.reference != other.reference {
+            changes.push("Reference link".to_string());
+        }
+
+        if self.palette != other.palette {
+            changes.push("Palette".to_string());
+        }
+
+        for kind in TraitKind::ALL {
+            if self.values(kind) != other.values(kind) {
+                changes.push(kind.name().to_string());
+            }
+        }
+
+        changes
+    }
+
+    /// Replaces the values picked for a trait. Single-choice traits take the first value, except
+    /// species which takes a second value as the other half of a hybrid.
+    pub fn set_values(&mut self, kind: TraitKind, values: Vec<String>) {
+        let first = values.first().cloned().unwrap_or_default();
+
+        match kind {
+            TraitKind::Species => {
+                // A custom species is forgotten once it is no longer picked
+                if self
+                    .custom_species
+                    .as_ref()
+                    .is_some_and(|c| !values.contains(&c.value))
+                {
+                    self.custom_species = None;
+                }
+
+                self.hybrid_with = values.get(1).cloned();
+                self.species = first;
+            }
+            TraitKind::BodyType => self.body_type = first,
+            TraitKind::Markings => self.markings = first,
+            TraitKind::Accessories => self.accessories = values,
+            TraitKind::Personality => self.personality = first,
+        }
+    }
+}
+
+/// Who can look up a fursona. Owners can always see their own characters.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    poise::ChoiceParameter,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Anyone, anywhere, and included in exports.
+    #[default]
+    Public,
+    /// Members of a server the owner is in, when looked up in that server.
+    #[name = "Server only"]
+    Server,
+    /// Only the owner.
+    Private,
+}
+
+impl Visibility {
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Visibility::Public => "🌐",
+            Visibility::Server => "🏠",
+            Visibility::Private => "🔒",
+        }
+    }
+}
+
+/// A species typed in by a user, waiting for moderators to add it to the server's catalog.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CustomSpecies {
+    /// The value stored as the fursona's species, used for the catalog option once approved.
+    pub value: String,
+    pub label: String,
+    /// The moderator's reason, if the species was rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<String>,
+}
+
+/// A past version of a character, saved when it was changed.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Revision {
+    pub character: String,
+    pub version: u32,
+    /// When this version was replaced.
+    pub saved_at: serenity::Timestamp,
+    /// The fields that were changed when this version was replaced.
+    pub changes: Vec<String>,
+    pub fursona: Fursona,
+}
+
+/// Every character belonging to a user, and which one they are currently using.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Profile {
+    pub active: Option<String>,
+    pub characters: Vec<Fursona>,
+    #[serde(default)]
+    pub history: Vec<Revision>,
+    /// The last version number given to each character, by lowercase name. Numbers only go up,
+    /// so undoing a change doesn't let the next one reuse its number.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub versions: HashMap<String, u32>,
+    /// Leaves the user's characters out of server exports.
+    #[serde(default)]
+    pub exclude_from_exports: bool,
+}
+
+impl Profile {
+    /// Finds a character by name, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&Fursona> {
+        self.characters.iter().find(|f| same_name(&f.name, name))
+    }
+
+    /// Returns the active character, falling back to the first one.
+    pub fn active(&self) -> Option<&Fursona> {
+        self.active
+            .as_deref()
+            .and_then(|name| self.get(name))
+            .or_else(|| self.characters.first())
+    }
+
+    /// Finds a character by name, or returns the active one if no name is given.
+    pub fn find(&self, name: Option<&str>) -> Option<&Fursona> {
+        match name {
+            Some(name) => self.get(name),
+            None => self.active(),
+        }
+    }
+
+    /// Adds a character, or replaces the one with the same name, and makes it active.
+    pub fn upsert(&mut self, mut fursona: Fursona) {
+        let name = fursona.name.clone();
+
+        if let Some(existing) = self.get(&name) {
+            // Visibility is a privacy choice, so overwriting the character doesn't reset it
+            fursona.visibility = existing.visibility;
+            self.replace(&name, fursona);
+        } else {
+            self.characters.push(fursona);
+        }
+
+        self.active = Some(name);
+    }
+
+    /// Replaces the named character, keeping its place in the list and whether it is active.
+    /// The old version is added to the history.
+    pub fn replace(&mut self, name: &str, fursona: Fursona) -> bool {
+        let Some(previous) = self.swap(name, fursona.clone()) else {
+            return false;
+        };
+
+        let changes = previous.changes(&fursona);
+
+        if changes.is_empty() {
+            return true;
+        }
+
+        let key = fursona.name.to_lowercase();
+
+        // Profiles saved before versions were counted only have their history to go on
+        let version = self
+            .history(&fursona.name)
+            .iter()
+            .map(|r| r.version)
+            .chain(self.versions.get(&key).copied())
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        self.versions.insert(key, version);
+
+        self.history.push(Revision {
+            character: fursona.name.clone(),
+            version,
+            saved_at: serenity::Timestamp::now(),
+            changes,
+            fursona: previous,
+        });
+
+        // Drop the oldest versions of this character once over the limit
+        while self.history(&fursona.name).len() > HISTORY_LIMIT {
+            let oldest = self
+                .history
+                .iter()
+                .position(|r| same_name(&r.character, &fursona.name))
+                .unwrap();
+
+            self.history.remove(oldest);
+        }
+
+        true
+    }
+
+    /// Returns the saved versions of a character, newest first.
+    pub fn history(&self, name: &str) -> Vec<&Revision> {
+        self.history
+            .iter()
+            .rev()
+            .filter(|r| same_name(&r.character, name))
+            .collect()
+    }
+
+    /// Returns a saved version of a character, or the newest one if no version is given.
+    pub fn revision(&self, name: &str, version: Option<u32>) -> Option<&Revision> {
+        self.history(name)
+            .into_iter()
+            .find(|r| version.is_none_or(|v| r.version == v))
+    }
+
+    /// Brings back a saved version of a character.
+    ///
+    /// Without a version this undoes the last change, discarding the newest revision. Restoring a
+    /// specific version keeps it and saves the current version, so the restore can be undone.
+    pub fn restore(&mut self, name: &str, version: Option<u32>) -> Option<Fursona> {
+        let mut revision = self.revision(name, version)?.clone();
+
+        // Visibility is a privacy choice rather than part of the character, so it isn't undone
+        if let Some(current) = self.get(name) {
+            revision.fursona.visibility = current.visibility;
         }
+
+        match version {
+            Some(_) => {
+                self.replace(name, revision.fursona.clone());
+            }
+            None => {
+                self.history
+                    .retain(|r| !(same_name(&r.character, name) && r.version == revision.version));
+                self.swap(name, revision.fursona.clone());
+            }
+        }
+
+        Some(revision.fursona)
+    }
+
+    /// Swaps out the named character without touching the history, returning the old version.
+    fn swap(&mut self, name: &str, fursona: Fursona) -> Option<Fursona> {
+        let index = self
+            .characters
+            .iter()
+            .position(|f| same_name(&f.name, name))?;
+
+        if self
+            .active
+            .as_deref()
+            .is_some_and(|active| same_name(active, name))
+        {
+            self.active = Some(fursona.name.clone());
+        }
+
+        // Keep the history attached to the character when it is renamed
+        for revision in &mut self.history {
+            if same_name(&revision.character, name) {
+                revision.character = fursona.name.clone();
+            }
+        }
+
+        if let Some(version) = self.versions.remove(&name.to_lowercase()) {
+            self.versions.insert(fursona.name.to_lowercase(), version);
+        }
+
+        Some(std::mem::replace(&mut self.characters[index], fursona))
     }
+
+    /// Removes a character by name, moving the active character if it was the one removed.
+    pub fn remove(&mut self, name: &str) -> Option<Fursona> {
+        let index = self
+            .characters
+            .iter()
+            .position(|f| same_name(&f.name, name))?;
+
+        let removed = self.characters.remove(index);
+
+        self.history
+            .retain(|r| !same_name(&r.character, &removed.name));
+        self.versions.remove(&removed.name.to_lowercase());
+
+        if self
+            .active
+            .as_deref()
+            .is_none_or(|active| same_name(active, &removed.name))
+        {
+            self.active = self.characters.first().map(|f| f.name.clone());
+        }
+
+        Some(removed)
+    }
+
+    /// Makes the named character active, returning `false` if there is no such character.
+    pub fn switch(&mut self, name: &str) -> bool {
+        match self.get(name) {
+            Some(fursona) => {
+                self.active = Some(fursona.name.clone());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Character names are unique per user regardless of case.
+pub fn same_name(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
 }
@@ -0,0 +1,301 @@
+use crate::{
+    catalog::{Picks, Rarity, TraitCatalog, TraitKind},
+    fursona::Fursona,
+    palette,
+};
+use poise::serenity_prelude as serenity;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::SliceRandom,
+    Rng, SeedableRng,
+};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+
+/// Trait values that are kept as they are instead of being rolled.
+pub type Locked = HashMap<TraitKind, Vec<String>>;
+
+/// Turns any text into a seed for [`seeded_rng`].
+pub fn seed(text: &str) -> u64 {
+    // FNV-1a, which unlike the std hasher is stable between releases
+    text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Returns the seed of the fursona destined for a user, which never changes.
+pub fn destined_seed(user_id: serenity::UserId) -> u64 {
+    seed(&format!("destined:{user_id}"))
+}
+
+/// Creates a random number generator that always gives the same rolls for a seed.
+///
+/// ChaCha is used rather than `StdRng` because its output is guaranteed not to change between
+/// releases of `rand`, so shared seeds keep rolling the same fursona.
+pub fn seeded_rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+/// How many times a fursona is rerolled before giving up on following the compatibility rules.
+const ATTEMPTS: usize = 20;
+
+/// Rolls a random fursona from the catalog, keeping any locked traits. The fursona is unnamed
+/// and gets a random palette.
+///
+/// Options are only picked when the catalog allows them alongside the traits rolled so far, and
+/// the whole fursona is rerolled if it still breaks a rule, e.g. by missing a required option.
+/// If the locked traits can't be satisfied the last roll is returned anyway, so callers should
+/// check it with [`TraitCatalog::conflicts`] before saving it.
+pub fn generate(catalog: &TraitCatalog, rng: &mut impl Rng, locked: &Locked) -> Fursona {
+    let mut fursona = attempt(catalog, rng, locked);
+
+    for _ in 1..ATTEMPTS {
+        if catalog.conflicts(&fursona).is_empty() {
+            break;
+        }
+
+        fursona = attempt(catalog, rng, locked);
+    }
+
+    fursona.palette = Some(palette::random(rng));
+
+    fursona
+}
+
+fn attempt(catalog: &TraitCatalog, rng: &mut impl Rng, locked: &Locked) -> Fursona {
+    let mut fursona = Fursona::new(
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        Vec::new(),
+        String::new(),
+    );
+
+    // Locked traits go first so the rolled ones are picked to fit them
+    for (&kind, values) in locked {
+        fursona.set_values(kind, values.clone());
+    }
+
+    for kind in TraitKind::ALL {
+        if !locked.contains_key(&kind) {
+            let values = roll(catalog, rng, &fursona, kind);
+
+            fursona.set_values(kind, values);
+        }
+    }
+
+    fursona
+}
+
+fn roll(
+    catalog: &TraitCatalog,
+    rng: &mut impl Rng,
+    fursona: &Fursona,
+    kind: TraitKind,
+) -> Vec<String> {
+    let options = catalog
+        .get(kind)
+        .options
+        .iter()
+        .filter(|o| catalog.allows(fursona, kind, o))
+        .collect::<Vec<_>>();
+
+    let hybrids = &catalog.hybrids;
+
+    let count = match kind {
+        TraitKind::Species if hybrids.enabled && rng.gen_bool(hybrids.chance) => 2,
+        _ => count(&catalog.picks(kind), rng),
+    };
+
+    // Options are weighted by rarity, and picked without replacement
+    options
+        .choose_multiple_weighted(rng, count, |o| o.weight())
+        .map(|picked| picked.map(|o| o.value.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Rolls how many options to pick, following the weights of each count if there are any.
+fn count(picks: &Picks, rng: &mut impl Rng) -> usize {
+    let min = picks.min as usize;
+
+    match WeightedIndex::new(&picks.weights) {
+        Ok(weights) => min + weights.sample(rng),
+        Err(_) => rng.gen_range(min..=picks.max as usize),
+    }
+}
+
+/// Returns the rarity of a fursona, which is that of its rarest trait.
+pub fn rarity(catalog: &TraitCatalog, fursona: &Fursona) -> Rarity {
+    TraitKind::ALL
+        .into_iter()
+        .flat_map(|kind| {
+            let set = catalog.get(kind);
+
+            fursona
+                .values(kind)
+                .into_iter()
+                .filter_map(|value| set.find(value).map(|o| o.rarity))
+                .collect::<Vec<_>>()
+        })
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &str = r#"
+        [hybrids]
+        enabled = false
+
+        [[species.options]]
+        label = "Dog"
+        value = "dog"
+        weight = 9.0
+
+        [[species.options]]
+        label = "Cat"
+        value = "cat"
+        weight = 1.0
+
+        [[body_type.options]]
+        label = "Slim"
+        value = "slim"
+
+        [[body_type.options]]
+        label = "Muscular"
+        value = "muscular"
+
+        [[markings.options]]
+        label = "Spots"
+        value = "spots"
+
+        [[markings.options]]
+        label = "Stripes"
+        value = "stripes"
+
+        [accessories]
+        picks = { min = 1, max = 3, weights = [0.0, 0.0, 1.0] }
+
+        [[accessories.options]]
+        label = "Collar"
+        value = "collar"
+
+        [[accessories.options]]
+        label = "Scarf"
+        value = "scarf"
+
+        [[accessories.options]]
+        label = "Glasses"
+        value = "glasses"
+
+        [[accessories.options]]
+        label = "Hat"
+        value = "hat"
+
+        [[personality.options]]
+        label = "Shy"
+        value = "shy"
+
+        [[personality.options]]
+        label = "Bold"
+        value = "bold"
+    "#;
+
+    fn catalog() -> TraitCatalog {
+        toml::from_str(CATALOG).unwrap()
+    }
+
+    fn traits(fursona: &Fursona) -> Vec<Vec<String>> {
+        TraitKind::ALL
+            .into_iter()
+            .map(|kind| fursona.values(kind).iter().map(|v| v.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_rolls_same_fursona() {
+        let catalog = catalog();
+        let locked = Locked::new();
+
+        for text in ["", "fluffy", "destined:1234"] {
+            let first = generate(&catalog, &mut seeded_rng(seed(text)), &locked);
+            let second = generate(&catalog, &mut seeded_rng(seed(text)), &locked);
+
+            assert_eq!(traits(&first), traits(&second));
+            assert_eq!(first.palette, second.palette);
+        }
+    }
+
+    #[test]
+    fn locked_traits_are_kept() {
+        let catalog = catalog();
+        let locked = Locked::from([
+            (TraitKind::Species, vec!["cat".to_string()]),
+            (
+                TraitKind::Accessories,
+                vec!["hat".to_string(), "scarf".to_string()],
+            ),
+        ]);
+
+        for i in 0..100 {
+            let fursona = generate(&catalog, &mut seeded_rng(i), &locked);
+
+            assert_eq!(fursona.values(TraitKind::Species), ["cat"]);
+            assert_eq!(fursona.values(TraitKind::Accessories), ["hat", "scarf"]);
+        }
+    }
+
+    #[test]
+    fn rarity_weights_are_honoured() {
+        let catalog = catalog();
+        let mut rng = seeded_rng(seed("weights"));
+        let rolls = 2000;
+
+        let dogs = (0..rolls)
+            .filter(|_| {
+                let fursona = generate(&catalog, &mut rng, &Locked::new());
+
+                fursona.values(TraitKind::Species) == ["dog"]
+            })
+            .count();
+
+        // Dogs are nine times as likely as cats
+        let share = dogs as f64 / rolls as f64;
+
+        assert!((0.85..0.95).contains(&share), "dogs were {share} of rolls");
+    }
+
+    #[test]
+    fn pick_counts_are_honoured() {
+        let mut catalog = catalog();
+        let mut rng = seeded_rng(seed("picks"));
+
+        // Only the weighted count is ever rolled
+        for _ in 0..100 {
+            let fursona = generate(&catalog, &mut rng, &Locked::new());
+
+            assert_eq!(fursona.values(TraitKind::Accessories).len(), 3);
+            assert_eq!(fursona.values(TraitKind::Species).len(), 1);
+        }
+
+        // Without weights every count from min to max is rolled
+        catalog.accessories.picks = Some(Picks {
+            min: 0,
+            max: 2,
+            weights: Vec::new(),
+        });
+
+        let mut counts = [0; 3];
+
+        for _ in 0..300 {
+            let fursona = generate(&catalog, &mut rng, &Locked::new());
+
+            counts[fursona.values(TraitKind::Accessories).len()] += 1;
+        }
+
+        assert!(counts.iter().all(|c| *c > 0), "counts were {counts:?}");
+    }
+}
@@ -0,0 +1,656 @@
+use crate::{
+    catalog::{TraitCatalog, TraitKind, TraitSet},
+    export::{self, ExportedFursona, Format, COLUMNS, LIST_SEPARATOR},
+    fursona::{same_name, Fursona, Profile},
+    palette::{self, Palette},
+    render, Error,
+};
+use poise::serenity_prelude as serenity;
+use std::collections::{HashMap, HashSet};
+
+/// How imported characters are combined with the ones users already have.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum Mode {
+    /// Adds the imported characters, overwriting any with the same name.
+    #[default]
+    Merge,
+    /// Replaces the characters this server imported before for every user in the file. Characters
+    /// users made themselves, or that other servers imported, are never removed.
+    Replace,
+}
+
+/// A fursona read from an import file.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// Where the fursona is in the file, for reporting problems.
+    pub row: usize,
+    pub user_id: serenity::UserId,
+    pub fursona: Fursona,
+}
+
+/// A row that won't be imported, and why.
+#[derive(Debug, Clone)]
+pub struct Invalid {
+    pub row: usize,
+    pub reason: String,
+}
+
+/// What an import would do, along with the profiles to save if it goes ahead.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub new: usize,
+    pub overwritten: usize,
+    /// Characters dropped by replacing a user's profile.
+    pub removed: usize,
+    pub invalid: Vec<Invalid>,
+    pub profiles: Vec<(serenity::UserId, Profile)>,
+}
+
+/// Reads the fursonas in an export file. Rows that can't be read or don't fit the catalog are
+/// returned as invalid, while a file that can't be read at all is an error.
+pub fn parse(
+    format: Format,
+    contents: &[u8],
+    catalog: &TraitCatalog,
+) -> Result<(Vec<Record>, Vec<Invalid>), Error> {
+    let rows = match format {
+        Format::Json => parse_json(contents)?,
+        Format::Csv => parse_csv(contents, catalog)?,
+        _ => return Err("only JSON and CSV files can be imported".into()),
+    };
+
+    let mut records = Vec::new();
+    let mut invalid = Vec::new();
+
+    for (row, result) in rows {
+        match result.and_then(|(user_id, fursona)| {
+            validate(catalog, &fursona)?;
+
+            Ok(Record {
+                row,
+                user_id,
+                fursona,
+            })
+        }) {
+            Ok(record) => records.push(record),
+            Err(reason) => invalid.push(Invalid { row, reason }),
+        }
+    }
+
+    Ok((records, invalid))
+}
+
+type Row = (usize, Result<(serenity::UserId, Fursona), String>);
+
+fn parse_json(contents: &[u8]) -> Result<Vec<Row>, Error> {
+    // Each record is read separately, so one bad record doesn't stop the rest being imported
+    let values: Vec<serde_json::Value> = serde_json::from_slice(contents)?;
+
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let result = serde_json::from_value::<ExportedFursona>(value)
+                .map(|e| (e.user_id, e.to_fursona()))
+                .map_err(|e| e.to_string());
+
+            (i + 1, result)
+        })
+        .collect())
+}
+
+fn parse_csv(contents: &[u8], catalog: &TraitCatalog) -> Result<Vec<Row>, Error> {
+    let mut reader = csv::Reader::from_reader(contents);
+
+    let headers = reader.headers()?.clone();
+    let mut columns = HashMap::new();
+
+    for column in COLUMNS {
+        let index = headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(column))
+            .ok_or_else(|| format!("the file is missing the `{column}` column"))?;
+
+        columns.insert(column, index);
+    }
+
+    let mut rows = Vec::new();
+
+    for (i, record) in reader.records().enumerate() {
+        // Rows are numbered as in a spreadsheet, after the header
+        let row = record
+            .as_ref()
+            .ok()
+            .and_then(|r| r.position())
+            .map_or(i + 2, |p| p.line() as usize);
+
+        let result = record.map_err(|e| e.to_string()).and_then(|record| {
+            let cell = |column: &str| {
+                export::unescape_cell(record.get(columns[column]).unwrap_or("").trim())
+            };
+
+            csv_fursona(catalog, cell)
+        });
+
+        rows.push((row, result));
+    }
+
+    Ok(rows)
+}
+
+/// Builds a fursona from a CSV row, turning labels back into catalog values.
+fn csv_fursona<'a>(
+    catalog: &TraitCatalog,
+    cell: impl Fn(&str) -> &'a str,
+) -> Result<(serenity::UserId, Fursona), String> {
+    let user_id = cell("User ID")
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .map(serenity::UserId::new)
+        .ok_or_else(|| format!("`{}` isn't a valid user ID", cell("User ID")))?;
+
+    let mut fursona = Fursona::new(
+        cell("Name").to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        Vec::new(),
+        String::new(),
+    );
+
+    for (kind, column) in [
+        (TraitKind::Species, "Species"),
+        (TraitKind::BodyType, "Body Type"),
+        (TraitKind::Markings, "Markings"),
+        (TraitKind::Accessories, "Accessories"),
+        (TraitKind::Personality, "Personality"),
+    ] {
+        let values = cell(column)
+            .split(LIST_SEPARATOR.trim())
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(|label| {
+                find_label(catalog.get(kind), label)
+                    .ok_or_else(|| format!("**{label}** isn't in the {} list", kind.name()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        fursona.set_values(kind, values);
+    }
+
+    let optional = |column| Some(cell(column).to_string()).filter(|s| !s.is_empty());
+
+    fursona.pronouns = optional("Pronouns");
+    fursona.bio = optional("Bio");
+    fursona.reference = optional("Reference");
+
+    if let Some(text) = optional("Palette") {
+        let colours = text
+            .split_whitespace()
+            .map(palette::parse_hex)
+            .collect::<Option<Vec<_>>>();
+
+        let Some([base, secondary, eyes]) = colours.and_then(|c| <[_; 3]>::try_from(c).ok()) else {
+            return Err(format!("`{text}` isn't a palette of three hex colours"));
+        };
+
+        fursona.palette = Some(Palette {
+            base,
+            secondary,
+            eyes,
+        });
+    }
+
+    if let Some(text) = optional("Created") {
+        fursona.created_at = serenity::Timestamp::parse(&text)
+            .map_err(|_| format!("`{text}` isn't a valid creation date"))?;
+    }
+
+    Ok((user_id, fursona))
+}
+
+/// Finds the value of an option by its label, or by its value.
+fn find_label(set: &TraitSet, label: &str) -> Option<String> {
+    set.options
+        .iter()
+        .find(|o| o.label.eq_ignore_ascii_case(label) || o.value == label)
+        .map(|o| o.value.clone())
+}
+
+/// Checks a fursona against the same rules as the commands that create them.
+fn validate(catalog: &TraitCatalog, fursona: &Fursona) -> Result<(), String> {
+    let name = fursona.name.trim();
+
+    if name.is_empty() {
+        return Err("the fursona has no name".to_string());
+    }
+
+    if name.chars().count() > 32 {
+        return Err(format!("**{name}** is longer than 32 characters"));
+    }
+
+    // The same limits as the details modal
+    for (field, text, max) in [
+        ("pronouns", &fursona.pronouns, 32),
+        ("bio", &fursona.bio, 300),
+        ("reference link", &fursona.reference, 200),
+    ] {
+        if text.as_ref().is_some_and(|t| t.chars().count() > max) {
+            return Err(format!("the {field} is longer than {max} characters"));
+        }
+    }
+
+    if let Some(link) = &fursona.reference {
+        if !render::is_link(link) {
+            return Err(
+                "the reference link must be a full link starting with `https://`".to_string(),
+            );
+        }
+    }
+
+    for kind in TraitKind::ALL {
+        let values = fursona.values(kind);
+
+        for value in values.iter().filter(|v| !v.is_empty()) {
+            let custom = kind == TraitKind::Species
+                && fursona
+                    .custom_species
+                    .as_ref()
+                    .is_some_and(|c| &c.value == value);
+
+            if catalog.get(kind).find(value).is_none() && !custom {
+                return Err(format!("`{value}` isn't in the {} list", kind.name()));
+            }
+        }
+
+        let count = values.iter().filter(|v| !v.is_empty()).count();
+
+        let (min, max) = if kind == TraitKind::Species {
+            (1, if catalog.hybrids.enabled { 2 } else { 1 })
+        } else {
+            let picks = catalog.picks(kind);
+
+            (picks.min as usize, picks.max as usize)
+        };
+
+        if count < min || count > max {
+            let expected = if min == max {
+                min.to_string()
+            } else {
+                format!("{min} to {max}")
+            };
+
+            return Err(format!(
+                "{count} {} picked, expected {expected}",
+                kind.name()
+            ));
+        }
+    }
+
+    let conflicts = catalog.conflicts(fursona);
+
+    if !conflicts.is_empty() {
+        return Err(conflicts.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Works out what importing the records into the existing profiles would do. Characters over
+/// the limit, or named twice for the same user, are reported as invalid.
+///
+/// Overwritten characters keep their visibility and history, with the old version saved as a
+/// revision. Replacing removes the characters the guild imported before that are missing from the
+/// file, history and all.
+pub fn plan(
+    guild_id: serenity::GuildId,
+    mode: Mode,
+    records: Vec<Record>,
+    mut existing: HashMap<serenity::UserId, Profile>,
+    max_characters: usize,
+) -> Report {
+    let mut report = Report::default();
+    // The profiles being built, along with the characters imported into each
+    let mut profiles: Vec<(serenity::UserId, Profile, Vec<String>)> = Vec::new();
+    let mut seen = HashSet::new();
+
+    for Record {
+        row,
+        user_id,
+        mut fursona,
+    } in records
+    {
+        fursona.name = fursona.name.trim().to_string();
+
+        if !seen.insert((user_id, fursona.name.to_lowercase())) {
+            report.invalid.push(Invalid {
+                row,
+                reason: format!("**{}** is in the file more than once", fursona.name),
+            });
+
+            continue;
+        }
+
+        let index = match profiles.iter().position(|(id, ..)| *id == user_id) {
+            Some(index) => index,
+            None => {
+                let profile = existing.remove(&user_id).unwrap_or_default();
+
+                profiles.push((user_id, profile, Vec::new()));
+                profiles.len() - 1
+            }
+        };
+
+        let (_, profile, imported) = &mut profiles[index];
+        let name = fursona.name.clone();
+
+        // Characters left out of a replaced profile are removed afterwards, so don't count them
+        let count = match mode {
+            Mode::Merge => profile.characters.len(),
+            Mode::Replace => {
+                let kept = profile
+                    .characters
+                    .iter()
+                    .filter(|f| !f.imported_into.contains(&guild_id))
+                    .count();
+
+                kept + imported.len()
+            }
+        };
+
+        fursona.imported_into = vec![guild_id];
+
+        if let Some(current) = profile.get(&name) {
+            // Visibility is the owner's choice, so it is never taken from the file
+            fursona.visibility = current.visibility;
+
+            for id in &current.imported_into {
+                if *id != guild_id {
+                    fursona.imported_into.push(*id);
+                }
+            }
+
+            profile.replace(&name, fursona);
+            report.overwritten += 1;
+        } else if count >= max_characters {
+            report.invalid.push(Invalid {
+                row,
+                reason: format!("the user already has {max_characters} characters"),
+            });
+
+            continue;
+        } else {
+            profile.characters.push(fursona);
+            report.new += 1;
+        }
+
+        imported.push(name);
+    }
+
+    for (user_id, mut profile, imported) in profiles {
+        if mode == Mode::Replace {
+            let removed = profile
+                .characters
+                .iter()
+                .filter(|f| f.imported_into.contains(&guild_id))
+                .filter(|f| !imported.iter().any(|n| same_name(n, &f.name)))
+                .map(|f| f.name.clone())
+                .collect::<Vec<_>>();
+
+            for name in &removed {
+                profile.remove(name);
+            }
+
+            report.removed += removed.len();
+        }
+
+        let active = profile.active.as_deref().and_then(|a| profile.get(a));
+
+        if active.is_none() {
+            profile.active = profile.characters.first().map(|f| f.name.clone());
+        }
+
+        report.profiles.push((user_id, profile));
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fursona::Visibility;
+
+    fn catalog() -> TraitCatalog {
+        toml::from_str(include_str!("../traits.toml")).unwrap()
+    }
+
+    fn entries() -> Vec<ExportedFursona> {
+        let created_at = serenity::Timestamp::parse("2024-05-01T12:00:00Z").unwrap();
+
+        let mut plain = Fursona::new(
+            "Rusty".to_string(),
+            "fox".to_string(),
+            "slim".to_string(),
+            "stripes".to_string(),
+            vec!["scarf".to_string(), "tail".to_string()],
+            "shy".to_string(),
+        );
+        plain.created_at = created_at;
+
+        let mut full = Fursona::new(
+            "Ash \"Smokey\", the Hybrid".to_string(),
+            "wolf".to_string(),
+            "fluffy".to_string(),
+            "none".to_string(),
+            Vec::new(),
+            "brave".to_string(),
+        );
+        full.hybrid_with = Some("fox".to_string());
+        full.pronouns = Some("they/them".to_string());
+        full.bio = Some("Likes naps; and snacks, mostly.\nAlso | pipes".to_string());
+        full.reference = Some("https://example.com/ash.png".to_string());
+        full.palette = Some(Palette::new(0xC8553D, 0xF4EDE4, 0xE0A526));
+        full.created_at = created_at;
+
+        vec![
+            ExportedFursona::new(serenity::UserId::new(1), "rusty".to_string(), &plain),
+            ExportedFursona::new(serenity::UserId::new(2), "ash".to_string(), &full),
+        ]
+    }
+
+    fn assert_round_trip(format: Format) {
+        let catalog = catalog();
+        let entries = entries();
+
+        let contents = export::export(format, &entries, &catalog).unwrap();
+        let (records, invalid) = parse(format, &contents, &catalog).unwrap();
+
+        assert!(invalid.is_empty(), "{invalid:?}");
+        assert_eq!(records.len(), entries.len());
+
+        for (record, entry) in records.iter().zip(&entries) {
+            let expected = entry.to_fursona();
+            let fursona = &record.fursona;
+
+            assert_eq!(record.user_id, entry.user_id);
+            assert_eq!(fursona.name, expected.name);
+            assert_eq!(fursona.pronouns, expected.pronouns);
+            assert_eq!(fursona.bio, expected.bio);
+            assert_eq!(fursona.reference, expected.reference);
+            assert_eq!(fursona.palette, expected.palette);
+            assert_eq!(fursona.created_at, expected.created_at);
+
+            for kind in TraitKind::ALL {
+                assert_eq!(fursona.values(kind), expected.values(kind));
+            }
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        assert_round_trip(Format::Json);
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        assert_round_trip(Format::Csv);
+    }
+
+    #[test]
+    fn csv_neutralises_formulas() {
+        let catalog = catalog();
+        let mut entries = entries();
+
+        entries[0].username = "@everyone".to_string();
+        entries[0].name = "=HYPERLINK(\"https://a.b\")".to_string();
+        entries[0].pronouns = Some("-they".to_string());
+        entries[1].bio = Some("+1 to naps".to_string());
+        entries[1].name = "'quoted".to_string();
+
+        let contents = export::export(Format::Csv, &entries, &catalog).unwrap();
+        let text = String::from_utf8(contents.clone()).unwrap();
+
+        assert!(text.contains(",'@everyone,"));
+        assert!(text.contains("'+1 to naps"));
+        assert!(text.contains("''quoted"));
+
+        let (records, invalid) = parse(Format::Csv, &contents, &catalog).unwrap();
+
+        assert!(invalid.is_empty(), "{invalid:?}");
+        assert_eq!(records[0].fursona.name, entries[0].name);
+        assert_eq!(records[0].fursona.pronouns, entries[0].pronouns);
+        assert_eq!(records[1].fursona.bio, entries[1].bio);
+        assert_eq!(records[1].fursona.name, entries[1].name);
+    }
+
+    #[test]
+    fn json_writes_every_key() {
+        let contents = export::export(Format::Json, &entries(), &catalog()).unwrap();
+        let values: Vec<serde_json::Value> = serde_json::from_slice(&contents).unwrap();
+
+        let keys = |value: &serde_json::Value| {
+            value
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(&values[0]), keys(&values[1]));
+        assert!(values[0]["pronouns"].is_null());
+        assert!(values[0].get("visibility").is_none());
+    }
+
+    fn record(row: usize, fursona: &Fursona) -> Record {
+        Record {
+            row,
+            user_id: serenity::UserId::new(1),
+            fursona: fursona.clone(),
+        }
+    }
+
+    const GUILD: serenity::GuildId = serenity::GuildId::new(10);
+
+    /// A profile with two characters. The first was made by the user, is hidden and has been
+    /// edited once, and the second was imported into `imported_into`.
+    fn profile(entries: &[ExportedFursona], imported_into: serenity::GuildId) -> Profile {
+        let mut first = entries[0].to_fursona();
+        first.visibility = Visibility::Private;
+
+        let mut second = entries[1].to_fursona();
+        second.imported_into = vec![imported_into];
+
+        let mut profile = Profile::default();
+        profile.upsert(first.clone());
+        first.bio = Some("Old bio".to_string());
+        profile.upsert(first);
+        profile.upsert(second);
+
+        profile
+    }
+
+    #[test]
+    fn merge_keeps_visibility_and_history() {
+        let entries = entries();
+        let existing = HashMap::from([(serenity::UserId::new(1), profile(&entries, GUILD))]);
+        let records = vec![record(1, &entries[0].to_fursona())];
+
+        let report = plan(GUILD, Mode::Merge, records, existing, 10);
+        let (_, profile) = &report.profiles[0];
+
+        assert_eq!(report.overwritten, 1);
+        assert_eq!(profile.characters.len(), 2);
+        assert_eq!(profile.characters[0].visibility, Visibility::Private);
+        assert_eq!(profile.history(&entries[0].name).len(), 2);
+    }
+
+    #[test]
+    fn replace_keeps_history_of_imported_characters() {
+        let entries = entries();
+        let existing = HashMap::from([(serenity::UserId::new(1), profile(&entries, GUILD))]);
+        let records = vec![record(1, &entries[0].to_fursona())];
+
+        let report = plan(GUILD, Mode::Replace, records, existing, 10);
+        let (_, profile) = &report.profiles[0];
+
+        assert_eq!((report.overwritten, report.removed), (1, 1));
+        assert_eq!(profile.characters.len(), 1);
+        assert_eq!(profile.characters[0].visibility, Visibility::Private);
+        assert_eq!(profile.history(&entries[0].name).len(), 2);
+        assert_eq!(profile.active.as_deref(), Some(entries[0].name.as_str()));
+    }
+
+    #[test]
+    fn replace_keeps_characters_imported_elsewhere() {
+        let entries = entries();
+        let other = serenity::GuildId::new(20);
+        let existing = HashMap::from([(serenity::UserId::new(1), profile(&entries, other))]);
+        let records = vec![record(1, &entries[0].to_fursona())];
+
+        let report = plan(GUILD, Mode::Replace, records, existing, 10);
+        let (_, profile) = &report.profiles[0];
+
+        assert_eq!(report.removed, 0);
+        assert_eq!(profile.characters.len(), 2);
+        assert_eq!(profile.characters[0].imported_into, [GUILD]);
+        assert_eq!(profile.characters[1].imported_into, [other]);
+    }
+
+    #[test]
+    fn broken_links_are_invalid() {
+        let mut fursona = entries()[0].to_fursona();
+
+        for link in [
+            "https://",
+            "https:// example.com",
+            "ftp://example.com",
+            "example.com",
+        ] {
+            fursona.reference = Some(link.to_string());
+
+            assert!(
+                validate(&catalog(), &fursona).is_err(),
+                "{link} was allowed"
+            );
+        }
+
+        fursona.reference = Some("https://example.com/ref.png".to_string());
+
+        assert!(validate(&catalog(), &fursona).is_ok());
+    }
+
+    #[test]
+    fn long_details_are_invalid() {
+        let mut fursona = entries()[0].to_fursona();
+        fursona.bio = Some("a".repeat(301));
+
+        assert!(validate(&catalog(), &fursona).is_err());
+
+        fursona.bio = Some("a".repeat(300));
+
+        assert!(validate(&catalog(), &fursona).is_ok());
+    }
+}
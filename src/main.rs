@@ -1,13 +1,21 @@
+mod audit;
+mod card;
+mod catalog;
 mod commands;
+mod export;
 mod fursona;
+mod generator;
+mod import;
+mod moderation;
+mod palette;
+mod render;
+mod settings;
+mod store;
+mod wizard;
 
 use dotenvy::dotenv;
 use poise::serenity_prelude as serenity;
-use std::{
-    collections::HashMap,
-    env::var,
-    sync::{Arc, Mutex},
-};
+use std::{env::var, path::PathBuf};
 
 // Types used by all command functions
 type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -15,7 +23,13 @@ type Context<'a> = poise::Context<'a, Data, Error>;
 
 // User data, which is stored and accessible in all command invocations
 struct Data {
-    fursonas: Arc<Mutex<HashMap<serenity::UserId, fursona::Fursona>>>,
+    fursonas: Box<dyn store::FursonaStore>,
+    catalogs: catalog::GuildCatalogs,
+    settings: settings::Settings,
+    species_queue: moderation::SpeciesQueue,
+    audit: audit::AuditLog,
+    assets_dir: PathBuf,
+    max_characters: usize,
 }
 
 async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
@@ -35,6 +49,22 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
     }
 }
 
+async fn event_handler(
+    ctx: &serenity::Context,
+    event: &serenity::FullEvent,
+    data: &Data,
+) -> Result<(), Error> {
+    // Buttons that outlive the command that sent them are handled here
+    if let serenity::FullEvent::InteractionCreate {
+        interaction: serenity::Interaction::Component(interaction),
+    } = event
+    {
+        moderation::handle_interaction(ctx, data, interaction).await?;
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() {
     dotenv().expect("Failed to load .env file");
@@ -42,8 +72,18 @@ async fn main() {
     let token = var("DISCORD_TOKEN").expect("missing DISCORD_TOKEN");
     let intents = serenity::GatewayIntents::non_privileged();
 
-    let guild_id =
-        serenity::GuildId::new(var("GUILD_ID").expect("missing GUILD_ID").parse().unwrap());
+    let data_dir = PathBuf::from(var("DATA_DIR").unwrap_or_else(|_| "data".to_string()));
+    let store_kind = var("STORE").unwrap_or_else(|_| "json".to_string());
+
+    let assets_dir = PathBuf::from(var("ASSETS_DIR").unwrap_or_else(|_| "assets".to_string()));
+
+    let max_characters = var("MAX_CHARACTERS")
+        .map(|v| v.parse().expect("MAX_CHARACTERS must be a number"))
+        .unwrap_or(5);
+
+    let traits_file = var("TRAITS_FILE").unwrap_or_else(|_| "traits.toml".to_string());
+    let catalog =
+        catalog::TraitCatalog::load(traits_file.as_ref()).expect("failed to load trait catalog");
 
     let options = poise::FrameworkOptions {
         on_error: |error| Box::pin(on_error(error)),
@@ -51,7 +91,11 @@ async fn main() {
             commands::ping(),
             commands::fursona(),
             commands::export_fursonas(),
+            commands::catalog(),
+            commands::moderation(),
+            commands::privacy(),
         ],
+        event_handler: |ctx, event, _framework, data| Box::pin(event_handler(ctx, event, data)),
         ..Default::default()
     };
 
@@ -59,11 +103,29 @@ async fn main() {
         .options(options)
         .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
-                poise::builtins::register_in_guild(ctx, &framework.options().commands, guild_id)
-                    .await?;
+                // Registered globally so every server gets its own catalog, settings and queue
+                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+
+                let fursonas = store::from_config(&store_kind, &data_dir)?;
+
+                println!("Loaded {} fursonas", fursonas.count().await?);
+
+                let catalogs =
+                    catalog::GuildCatalogs::open(catalog, data_dir.join("catalogs.json"))?;
+
+                let settings = settings::Settings::open(data_dir.join("settings.json"))?;
+                let species_queue =
+                    moderation::SpeciesQueue::open(data_dir.join("species_queue.json"))?;
+                let audit = audit::AuditLog::open(data_dir.join("audit.log"));
 
                 Ok(Data {
-                    fursonas: Arc::new(Mutex::new(HashMap::new())),
+                    fursonas,
+                    catalogs,
+                    settings,
+                    species_queue,
+                    audit,
+                    assets_dir,
+                    max_characters,
                 })
             })
         })
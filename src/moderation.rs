@@ -0,0 +1,420 @@
+use crate::{
+    catalog::{TraitOption, MAX_OPTIONS},
+    fursona::CustomSpecies,
+    store, Data, Error,
+};
+use poise::serenity_prelude as serenity;
+use std::{path::PathBuf, sync::Mutex, time::Duration};
+
+const APPROVE_PREFIX: &str = "species_approve:";
+const REJECT_PREFIX: &str = "species_reject:";
+
+/// A custom species waiting for a moderator to approve or reject it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Submission {
+    pub id: u64,
+    pub guild_id: serenity::GuildId,
+    pub user_id: serenity::UserId,
+    /// The character the species was typed in for, for display.
+    pub character: String,
+    pub label: String,
+    pub value: String,
+    pub submitted_at: serenity::Timestamp,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct QueueFile {
+    next_id: u64,
+    submissions: Vec<Submission>,
+}
+
+/// Custom species waiting for review in every server, saved as JSON.
+pub struct SpeciesQueue {
+    path: PathBuf,
+    queue: Mutex<QueueFile>,
+}
+
+impl SpeciesQueue {
+    /// Loads the queue stored at `path`.
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let queue = store::load_json(&path)?.unwrap_or_default();
+
+        Ok(Self {
+            path,
+            queue: Mutex::new(queue),
+        })
+    }
+
+    /// Adds a species to the queue, unless the same user already submitted it in that guild.
+    pub fn push(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        character: String,
+        label: String,
+        value: String,
+    ) -> Result<Option<Submission>, Error> {
+        let mut queue = self.queue.lock().unwrap();
+
+        let duplicate = queue
+            .submissions
+            .iter()
+            .any(|s| s.guild_id == guild_id && s.user_id == user_id && s.value == value);
+
+        if duplicate {
+            return Ok(None);
+        }
+
+        queue.next_id += 1;
+
+        let submission = Submission {
+            id: queue.next_id,
+            guild_id,
+            user_id,
+            character,
+            label,
+            value,
+            submitted_at: serenity::Timestamp::now(),
+        };
+
+        queue.submissions.push(submission.clone());
+        store::save_json(&self.path, &*queue)?;
+
+        Ok(Some(submission))
+    }
+
+    /// Removes a submission from the queue once it has been reviewed.
+    pub fn take(&self, id: u64) -> Result<Option<Submission>, Error> {
+        let mut queue = self.queue.lock().unwrap();
+
+        let Some(index) = queue.submissions.iter().position(|s| s.id == id) else {
+            return Ok(None);
+        };
+
+        let submission = queue.submissions.remove(index);
+        store::save_json(&self.path, &*queue)?;
+
+        Ok(Some(submission))
+    }
+
+    pub fn get(&self, id: u64) -> Option<Submission> {
+        let queue = self.queue.lock().unwrap();
+
+        queue.submissions.iter().find(|s| s.id == id).cloned()
+    }
+
+    /// Returns every submission made by a user, in any guild.
+    pub fn by_user(&self, user_id: serenity::UserId) -> Vec<Submission> {
+        let queue = self.queue.lock().unwrap();
+
+        queue
+            .submissions
+            .iter()
+            .filter(|s| s.user_id == user_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Removes every submission made by a user, returning how many there were.
+    pub fn forget(&self, user_id: serenity::UserId) -> Result<usize, Error> {
+        let mut queue = self.queue.lock().unwrap();
+
+        let before = queue.submissions.len();
+        queue.submissions.retain(|s| s.user_id != user_id);
+        let removed = before - queue.submissions.len();
+
+        if removed > 0 {
+            store::save_json(&self.path, &*queue)?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Returns the submissions waiting for review in a guild, oldest first.
+    pub fn pending(&self, guild_id: serenity::GuildId) -> Vec<Submission> {
+        let queue = self.queue.lock().unwrap();
+
+        queue
+            .submissions
+            .iter()
+            .filter(|s| s.guild_id == guild_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Builds the embed moderators review a submission in.
+pub fn review_embed(submission: &Submission) -> serenity::CreateEmbed {
+    serenity::CreateEmbed::new()
+        .title("Species request")
+        .description(format!(
+            "<@{}> would like to add **{}** to the species list, for their character **{}**.",
+            submission.user_id, submission.label, submission.character
+        ))
+        .field("Value", format!("`{}`", submission.value), true)
+        .timestamp(submission.submitted_at)
+}
+
+/// Builds the approve and reject buttons for a submission.
+pub fn review_buttons(submission: &Submission) -> Vec<serenity::CreateActionRow> {
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(format!("{APPROVE_PREFIX}{}", submission.id))
+            .label("Approve")
+            .style(serenity::ButtonStyle::Success),
+        serenity::CreateButton::new(format!("{REJECT_PREFIX}{}", submission.id))
+            .label("Reject")
+            .style(serenity::ButtonStyle::Danger),
+    ])]
+}
+
+/// Handles the approve and reject buttons on review messages. These outlive the command that
+/// posted them, so they are handled from the event handler rather than a collector.
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::ComponentInteraction,
+) -> Result<(), Error> {
+    let custom_id = interaction.data.custom_id.as_str();
+
+    let (approve, id) = if let Some(id) = custom_id.strip_prefix(APPROVE_PREFIX) {
+        (true, id)
+    } else if let Some(id) = custom_id.strip_prefix(REJECT_PREFIX) {
+        (false, id)
+    } else {
+        return Ok(());
+    };
+
+    let is_moderator = interaction
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.manage_guild());
+
+    if !is_moderator {
+        return respond(
+            ctx,
+            interaction,
+            "Only moderators can review species requests",
+        )
+        .await;
+    }
+
+    let Some(submission) = id.parse().ok().and_then(|id| data.species_queue.get(id)) else {
+        return respond(ctx, interaction, "This request has already been reviewed").await;
+    };
+
+    if approve {
+        approve_submission(ctx, data, interaction, submission).await
+    } else {
+        reject_submission(ctx, data, interaction, submission).await
+    }
+}
+
+async fn approve_submission(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::ComponentInteraction,
+    submission: Submission,
+) -> Result<(), Error> {
+    let option = TraitOption::new(submission.label.clone(), submission.value.clone());
+
+    // Species typed in before reserved values were refused could still be waiting
+    if let Some(problem) = option.problem() {
+        return respond(
+            ctx,
+            interaction,
+            &format!(
+                "Couldn't add **{}**: {problem}, reject it instead",
+                submission.label
+            ),
+        )
+        .await;
+    }
+
+    let added = data.catalogs.update(submission.guild_id, |catalog| {
+        let species = &mut catalog.species;
+
+        if species.find(&submission.value).is_some() {
+            return Ok(true);
+        }
+
+        if species.options.len() >= MAX_OPTIONS {
+            return Ok(false);
+        }
+
+        species.options.push(option.clone());
+
+        Ok(true)
+    })?;
+
+    if !added {
+        return respond(
+            ctx,
+            interaction,
+            "The species list is full, remove a species with `/catalog remove` first",
+        )
+        .await;
+    }
+
+    data.species_queue.take(submission.id)?;
+
+    // The species is in the catalog now, so it no longer needs marking
+    update_characters(data, &submission, |_| None).await?;
+
+    let embed = review_embed(&submission)
+        .colour(serenity::Colour::DARK_GREEN)
+        .field("Approved by", format!("<@{}>", interaction.user.id), true);
+
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+
+    notify(
+        ctx,
+        &submission,
+        format!(
+            "Your species **{}** was approved and is now in the species list!",
+            submission.label
+        ),
+    )
+    .await;
+
+    Ok(())
+}
+
+async fn reject_submission(
+    ctx: &serenity::Context,
+    data: &Data,
+    interaction: &serenity::ComponentInteraction,
+    submission: Submission,
+) -> Result<(), Error> {
+    let input =
+        serenity::CreateInputText::new(serenity::InputTextStyle::Paragraph, "Reason", "reason")
+            .max_length(200)
+            .required(true);
+
+    let modal = serenity::CreateQuickModal::new(format!("Reject {}", submission.label))
+        .timeout(Duration::from_secs(300))
+        .field(input);
+
+    let Some(response) = interaction.quick_modal(ctx, modal).await? else {
+        return Ok(());
+    };
+
+    let reason = response.inputs.into_iter().next().unwrap_or_default();
+    let reason = reason.trim().to_string();
+
+    // Another moderator may have reviewed it while the modal was open
+    if data.species_queue.take(submission.id)?.is_none() {
+        response
+            .interaction
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::Message(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .ephemeral(true)
+                        .content("This request has already been reviewed"),
+                ),
+            )
+            .await?;
+
+        return Ok(());
+    }
+
+    update_characters(data, &submission, |custom| {
+        Some(CustomSpecies {
+            rejected: Some(reason.clone()),
+            ..custom
+        })
+    })
+    .await?;
+
+    let embed = review_embed(&submission)
+        .colour(serenity::Colour::RED)
+        .field("Rejected by", format!("<@{}>", interaction.user.id), true)
+        .field("Reason", &reason, false);
+
+    response
+        .interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .embed(embed)
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+
+    notify(
+        ctx,
+        &submission,
+        format!(
+            "Your species **{}** was rejected: {reason}\nPick another with `/fursona edit`.",
+            submission.label
+        ),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Updates the custom species of every character of the submitter that uses it.
+async fn update_characters(
+    data: &Data,
+    submission: &Submission,
+    f: impl Fn(CustomSpecies) -> Option<CustomSpecies>,
+) -> Result<(), Error> {
+    let Some(mut profile) = data.fursonas.get(submission.user_id).await? else {
+        return Ok(());
+    };
+
+    for fursona in &mut profile.characters {
+        if let Some(custom) = fursona
+            .custom_species
+            .take_if(|c| c.value == submission.value)
+        {
+            fursona.custom_species = f(custom);
+        }
+    }
+
+    data.fursonas.put(submission.user_id, profile).await
+}
+
+async fn respond(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    content: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::Message(
+                serenity::CreateInteractionResponseMessage::new()
+                    .ephemeral(true)
+                    .content(content),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Lets the submitter know their species was reviewed. Users with closed DMs are skipped.
+async fn notify(ctx: &serenity::Context, submission: &Submission, content: String) {
+    let message = serenity::CreateMessage::new().content(content);
+
+    if let Err(e) = submission.user_id.direct_message(ctx, message).await {
+        println!(
+            "Couldn't notify {} of their species review: {}",
+            submission.user_id, e
+        );
+    }
+}
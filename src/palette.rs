@@ -0,0 +1,180 @@
+use crate::render;
+use poise::serenity_prelude as serenity;
+use rand::Rng;
+
+/// The colours of a fursona.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Palette {
+    /// The main fur, scale or feather colour.
+    #[serde(with = "hex")]
+    pub base: serenity::Colour,
+    #[serde(with = "hex")]
+    pub secondary: serenity::Colour,
+    #[serde(with = "hex")]
+    pub eyes: serenity::Colour,
+}
+
+impl Palette {
+    pub const fn new(base: u32, secondary: u32, eyes: u32) -> Self {
+        Self {
+            base: serenity::Colour::new(base),
+            secondary: serenity::Colour::new(secondary),
+            eyes: serenity::Colour::new(eyes),
+        }
+    }
+
+    /// Returns each colour with its name, in the order they are shown.
+    pub fn colours(&self) -> [(&'static str, serenity::Colour); 3] {
+        [
+            ("Base", self.base),
+            ("Secondary", self.secondary),
+            ("Eyes", self.eyes),
+        ]
+    }
+
+    /// Describes the palette as hex codes, e.g. "Base `#C8553D` · Secondary ...".
+    pub fn describe(&self) -> String {
+        self.colours()
+            .iter()
+            .map(|(name, colour)| format!("{name} `{}`", to_hex(*colour)))
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+}
+
+/// A named palette users can pick instead of typing hex codes.
+pub struct Preset {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub palette: Palette,
+}
+
+pub const PRESETS: [Preset; 8] = [
+    Preset {
+        id: "red_fox",
+        name: "Red Fox",
+        palette: Palette::new(0xC8553D, 0xF4EDE4, 0xE0A526),
+    },
+    Preset {
+        id: "arctic_fox",
+        name: "Arctic Fox",
+        palette: Palette::new(0xF2F4F7, 0xB8C4D6, 0x4A90C2),
+    },
+    Preset {
+        id: "grey_wolf",
+        name: "Grey Wolf",
+        palette: Palette::new(0x7D7F84, 0xD9D4CC, 0xD4A017),
+    },
+    Preset {
+        id: "tabby",
+        name: "Tabby",
+        palette: Palette::new(0xB9814A, 0x5C3A1E, 0x6FA34B),
+    },
+    Preset {
+        id: "black_cat",
+        name: "Black Cat",
+        palette: Palette::new(0x232323, 0x3D3D45, 0xE5C643),
+    },
+    Preset {
+        id: "golden_retriever",
+        name: "Golden Retriever",
+        palette: Palette::new(0xD9A55B, 0xF3DDB3, 0x5A3A22),
+    },
+    Preset {
+        id: "dalmatian",
+        name: "Dalmatian",
+        palette: Palette::new(0xFAFAFA, 0x1E1E1E, 0x4E7CA6),
+    },
+    Preset {
+        id: "neon",
+        name: "Neon",
+        palette: Palette::new(0x1B1B3A, 0x00F5D4, 0xF15BB5),
+    },
+];
+
+/// Finds a preset by its id.
+pub fn preset(id: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.id == id)
+}
+
+/// Parses a hex colour such as `#FF8800`, `ff8800` or `#f80`.
+pub fn parse_hex(text: &str) -> Option<serenity::Colour> {
+    let digits = text.trim().trim_start_matches('#');
+
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let value = match digits.len() {
+        6 => u32::from_str_radix(digits, 16).ok()?,
+        // Shorthand, each digit is doubled
+        3 => digits.chars().fold(0, |value, c| {
+            let digit = c.to_digit(16).unwrap();
+
+            (value << 8) | (digit << 4) | digit
+        }),
+        _ => return None,
+    };
+
+    Some(serenity::Colour::new(value))
+}
+
+/// Formats a colour as a hex code, e.g. `#C8553D`.
+pub fn to_hex(colour: serenity::Colour) -> String {
+    format!("#{}", colour.hex())
+}
+
+/// Rolls a random palette whose colours follow a colour harmony, so they go well together.
+pub fn random(rng: &mut impl Rng) -> Palette {
+    let hue = rng.gen_range(0.0..360.0);
+
+    // Hue offsets of the secondary and eye colours from the base
+    let (secondary, eyes) = match rng.gen_range(0..4) {
+        // Complementary
+        0 => (0.0, 180.0),
+        // Analogous
+        1 => (30.0, -30.0),
+        // Triadic
+        2 => (120.0, 240.0),
+        // Split complementary
+        _ => (150.0, 210.0),
+    };
+
+    // Fur colours are kept muted, while eyes are bright
+    let saturation = rng.gen_range(0.3..0.7);
+    let value = rng.gen_range(0.45..0.9);
+
+    Palette {
+        base: hsv(hue, saturation, value),
+        secondary: hsv(hue + secondary, saturation * 0.6, (value + 0.25).min(1.0)),
+        eyes: hsv(hue + eyes, rng.gen_range(0.7..1.0), rng.gen_range(0.8..1.0)),
+    }
+}
+
+fn hsv(hue: f32, saturation: f32, value: f32) -> serenity::Colour {
+    let (r, g, b) = render::hsv_to_rgb(hue.rem_euclid(360.0), saturation, value);
+
+    serenity::Colour::from_rgb(r, g, b)
+}
+
+/// Stores colours as hex codes rather than plain numbers, to keep the data files readable.
+mod hex {
+    use poise::serenity_prelude as serenity;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        colour: &serenity::Colour,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::to_hex(*colour))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<serenity::Colour, D::Error> {
+        let text = String::deserialize(deserializer)?;
+
+        super::parse_hex(&text)
+            .ok_or_else(|| D::Error::custom(format!("invalid hex colour `{text}`")))
+    }
+}
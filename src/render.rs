@@ -0,0 +1,143 @@
+use crate::{
+    catalog::{TraitCatalog, TraitKind},
+    fursona::Fursona,
+    generator,
+};
+use poise::serenity_prelude as serenity;
+
+/// Builds the embed used everywhere a fursona is shown.
+pub fn fursona_embed(
+    fursona: &Fursona,
+    owner: &serenity::User,
+    catalog: &TraitCatalog,
+) -> serenity::CreateEmbed {
+    let title = match &fursona.pronouns {
+        Some(pronouns) => format!("{} ({pronouns})", fursona.name),
+        None => fursona.name.clone(),
+    };
+
+    let mut embed = serenity::CreateEmbed::new()
+        .author(
+            serenity::CreateEmbedAuthor::new(format!("{}'s fursona", owner.display_name()))
+                .icon_url(owner.face()),
+        )
+        .title(title)
+        .colour(colour(fursona))
+        .footer(serenity::CreateEmbedFooter::new("Created"))
+        .timestamp(fursona.created_at);
+
+    for kind in TraitKind::ALL {
+        embed = embed.field(kind.name(), trait_value(fursona, catalog, kind), true);
+    }
+
+    if let Some(bio) = &fursona.bio {
+        embed = embed.description(bio);
+    }
+
+    // Links saved before they were checked could make Discord reject the whole embed
+    if let Some(reference) = fursona.reference.as_ref().filter(|r| is_link(r)) {
+        embed = embed.url(reference);
+    }
+
+    if let Some(palette) = &fursona.palette {
+        embed = embed.field("Palette", palette.describe(), false);
+    }
+
+    embed
+}
+
+/// Returns the labels picked for a trait, formatted for display. Hybrids are named by the
+/// catalog's hybrid rules.
+pub fn trait_value(fursona: &Fursona, catalog: &TraitCatalog, kind: TraitKind) -> String {
+    if kind == TraitKind::Species {
+        return catalog.species_name(fursona);
+    }
+
+    let set = catalog.get(kind);
+    let labels = fursona
+        .values(kind)
+        .iter()
+        .map(|v| set.label(v))
+        .collect::<Vec<_>>();
+
+    if labels.is_empty() {
+        "None".to_string()
+    } else {
+        join_list(&labels)
+    }
+}
+
+/// Returns whether text is a full web link, which Discord requires for embed links.
+pub fn is_link(text: &str) -> bool {
+    // Spaces would be escaped by the parser, but Discord rejects them as they are
+    !text.contains(char::is_whitespace)
+        && url::Url::parse(text).is_ok_and(|url| {
+            matches!(url.scheme(), "http" | "https")
+                && url.host_str().is_some_and(|h| !h.is_empty())
+        })
+}
+
+/// Joins items into an English list, e.g. "Hat, Scarf and Wings".
+pub fn join_list<S: AsRef<str>>(items: &[S]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.as_ref().to_string(),
+        [rest @ .., last] => format!(
+            "{} and {}",
+            rest.iter()
+                .map(|s| s.as_ref())
+                .collect::<Vec<_>>()
+                .join(", "),
+            last.as_ref()
+        ),
+    }
+}
+
+/// Shortens text to at most `max` characters, ending it with "…" if anything was cut.
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let mut short = text.chars().take(max.saturating_sub(1)).collect::<String>();
+    short.push('…');
+
+    short
+}
+
+/// Returns the base colour of a fursona's palette, or derives a stable colour from its traits
+/// so the same fursona always gets the same colour bar.
+pub fn colour(fursona: &Fursona) -> serenity::Colour {
+    if let Some(palette) = &fursona.palette {
+        return palette.base;
+    }
+
+    let hash = generator::seed(&format!(
+        "{}{}{}",
+        fursona.species, fursona.markings, fursona.personality
+    ));
+
+    let (r, g, b) = hsv_to_rgb((hash % 360) as f32, 0.55, 0.9);
+
+    serenity::Colour::from_rgb(r, g, b)
+}
+
+/// Converts a hue in degrees and saturation/value in `0.0..=1.0` to RGB.
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+    let c = value * saturation;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = value - c;
+
+    let (r, g, b) = match hue as u32 {
+        0..=59 => (c, x, 0.0),
+        60..=119 => (x, c, 0.0),
+        120..=179 => (0.0, c, x),
+        180..=239 => (0.0, x, c),
+        240..=299 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    let to_byte = |v: f32| ((v + m) * 255.0).round() as u8;
+
+    (to_byte(r), to_byte(g), to_byte(b))
+}
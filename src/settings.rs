@@ -0,0 +1,117 @@
+use crate::{store, Error};
+use poise::serenity_prelude as serenity;
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+
+/// Per-server settings changed by admins through commands.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct GuildSettings {
+    /// Where species requests are posted for moderators to review.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mod_channel: Option<serenity::ChannelId>,
+    /// The permission members need to export everyone's fursonas.
+    #[serde(default)]
+    pub export_permission: ExportPermission,
+    /// Roles allowed to export even without the permission.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub export_roles: Vec<serenity::RoleId>,
+}
+
+impl GuildSettings {
+    /// Returns whether a member may export everyone's fursonas.
+    pub fn can_export(&self, member: &serenity::Member) -> bool {
+        let permitted = member
+            .permissions
+            .is_some_and(|p| p.administrator() || p.contains(self.export_permission.permissions()));
+
+        permitted || member.roles.iter().any(|r| self.export_roles.contains(r))
+    }
+}
+
+/// The permissions admins can require for exports.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    poise::ChoiceParameter,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportPermission {
+    #[default]
+    #[name = "Manage Server"]
+    ManageGuild,
+    Administrator,
+    #[name = "Manage Roles"]
+    ManageRoles,
+    #[name = "Manage Messages"]
+    ManageMessages,
+    #[name = "Timeout Members"]
+    ModerateMembers,
+}
+
+impl ExportPermission {
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportPermission::ManageGuild => "Manage Server",
+            ExportPermission::Administrator => "Administrator",
+            ExportPermission::ManageRoles => "Manage Roles",
+            ExportPermission::ManageMessages => "Manage Messages",
+            ExportPermission::ModerateMembers => "Timeout Members",
+        }
+    }
+
+    pub fn permissions(self) -> serenity::Permissions {
+        match self {
+            ExportPermission::ManageGuild => serenity::Permissions::MANAGE_GUILD,
+            ExportPermission::Administrator => serenity::Permissions::ADMINISTRATOR,
+            ExportPermission::ManageRoles => serenity::Permissions::MANAGE_ROLES,
+            ExportPermission::ManageMessages => serenity::Permissions::MANAGE_MESSAGES,
+            ExportPermission::ModerateMembers => serenity::Permissions::MODERATE_MEMBERS,
+        }
+    }
+}
+
+/// The settings of every server, saved as JSON.
+pub struct Settings {
+    path: PathBuf,
+    guilds: Mutex<HashMap<serenity::GuildId, GuildSettings>>,
+}
+
+impl Settings {
+    /// Loads the settings stored at `path`.
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let guilds = store::load_json(&path)?.unwrap_or_default();
+
+        Ok(Self {
+            path,
+            guilds: Mutex::new(guilds),
+        })
+    }
+
+    /// Returns a guild's settings, or the defaults if it hasn't changed any.
+    pub fn get(&self, guild_id: serenity::GuildId) -> GuildSettings {
+        self.guilds
+            .lock()
+            .unwrap()
+            .get(&guild_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Applies a change to a guild's settings and saves them.
+    pub fn update(
+        &self,
+        guild_id: serenity::GuildId,
+        f: impl FnOnce(&mut GuildSettings),
+    ) -> Result<(), Error> {
+        let mut guilds = self.guilds.lock().unwrap();
+
+        f(guilds.entry(guild_id).or_default());
+
+        store::save_json(&self.path, &*guilds)
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{
+    fursona::{Fursona, Profile},
+    Error,
+};
+use poise::serenity_prelude as serenity;
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+const FURSONAS_FILE: &str = "fursonas.json";
+
+/// Storage backend for fursonas, holding a profile of characters per user.
+#[async_trait::async_trait]
+pub trait FursonaStore: Send + Sync {
+    /// Returns a user's profile, if they have one.
+    async fn get(&self, user_id: serenity::UserId) -> Result<Option<Profile>, Error>;
+
+    /// Sets a user's profile, replacing any existing one.
+    async fn put(&self, user_id: serenity::UserId, profile: Profile) -> Result<(), Error>;
+
+    /// Removes a user's profile, returning it if there was one.
+    async fn delete(&self, user_id: serenity::UserId) -> Result<Option<Profile>, Error>;
+
+    /// Returns every stored profile.
+    async fn list(&self) -> Result<Vec<(serenity::UserId, Profile)>, Error>;
+
+    /// Returns the number of stored profiles.
+    async fn count(&self) -> Result<usize, Error>;
+}
+
+/// Creates the store selected by the `STORE` setting (`json` or `memory`).
+pub fn from_config(kind: &str, data_dir: &Path) -> Result<Box<dyn FursonaStore>, Error> {
+    match kind {
+        "memory" => Ok(Box::new(MemoryStore::default())),
+        "json" => Ok(Box::new(JsonStore::open(data_dir.join(FURSONAS_FILE))?)),
+        other => Err(format!("unknown store `{other}`, expected `json` or `memory`").into()),
+    }
+}
+
+/// A store that only keeps fursonas in memory, losing them on restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    fursonas: Mutex<HashMap<serenity::UserId, Profile>>,
+}
+
+#[async_trait::async_trait]
+impl FursonaStore for MemoryStore {
+    async fn get(&self, user_id: serenity::UserId) -> Result<Option<Profile>, Error> {
+        Ok(self.fursonas.lock().unwrap().get(&user_id).cloned())
+    }
+
+    async fn put(&self, user_id: serenity::UserId, profile: Profile) -> Result<(), Error> {
+        self.fursonas.lock().unwrap().insert(user_id, profile);
+
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: serenity::UserId) -> Result<Option<Profile>, Error> {
+        Ok(self.fursonas.lock().unwrap().remove(&user_id))
+    }
+
+    async fn list(&self) -> Result<Vec<(serenity::UserId, Profile)>, Error> {
+        let fursonas = self.fursonas.lock().unwrap();
+
+        Ok(fursonas.iter().map(|(k, v)| (*k, v.clone())).collect())
+    }
+
+    async fn count(&self) -> Result<usize, Error> {
+        Ok(self.fursonas.lock().unwrap().len())
+    }
+}
+
+/// A store that keeps fursonas in memory and writes them to a JSON file on every change.
+pub struct JsonStore {
+    path: PathBuf,
+    fursonas: Mutex<HashMap<serenity::UserId, Profile>>,
+}
+
+impl JsonStore {
+    /// Opens the store at `path`, starting empty if the file doesn't exist yet.
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let stored: HashMap<serenity::UserId, StoredProfile> =
+            load_json(&path)?.unwrap_or_default();
+
+        let fursonas = stored
+            .into_iter()
+            .map(|(user_id, stored)| (user_id, stored.into()))
+            .collect();
+
+        Ok(Self {
+            path,
+            fursonas: Mutex::new(fursonas),
+        })
+    }
+
+    fn save(&self, fursonas: &HashMap<serenity::UserId, Profile>) -> Result<(), Error> {
+        save_json(&self.path, fursonas)
+    }
+}
+
+#[async_trait::async_trait]
+impl FursonaStore for JsonStore {
+    async fn get(&self, user_id: serenity::UserId) -> Result<Option<Profile>, Error> {
+        Ok(self.fursonas.lock().unwrap().get(&user_id).cloned())
+    }
+
+    async fn put(&self, user_id: serenity::UserId, profile: Profile) -> Result<(), Error> {
+        let mut fursonas = self.fursonas.lock().unwrap();
+
+        fursonas.insert(user_id, profile);
+
+        self.save(&fursonas)
+    }
+
+    async fn delete(&self, user_id: serenity::UserId) -> Result<Option<Profile>, Error> {
+        let mut fursonas = self.fursonas.lock().unwrap();
+
+        let removed = fursonas.remove(&user_id);
+
+        if removed.is_some() {
+            self.save(&fursonas)?;
+        }
+
+        Ok(removed)
+    }
+
+    async fn list(&self) -> Result<Vec<(serenity::UserId, Profile)>, Error> {
+        let fursonas = self.fursonas.lock().unwrap();
+
+        Ok(fursonas.iter().map(|(k, v)| (*k, v.clone())).collect())
+    }
+
+    async fn count(&self) -> Result<usize, Error> {
+        Ok(self.fursonas.lock().unwrap().len())
+    }
+}
+
+/// A profile as found on disk, which may predate users having multiple characters.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredProfile {
+    Profile(Profile),
+    Single(Box<Fursona>),
+}
+
+impl From<StoredProfile> for Profile {
+    fn from(stored: StoredProfile) -> Self {
+        match stored {
+            StoredProfile::Profile(profile) => profile,
+            StoredProfile::Single(mut fursona) => {
+                if fursona.name.is_empty() {
+                    fursona.name = "Fursona".to_string();
+                }
+
+                let mut profile = Profile::default();
+                profile.upsert(*fursona);
+                profile
+            }
+        }
+    }
+}
+
+/// Reads a JSON file, returning `None` if it doesn't exist.
+pub fn load_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read(path)?;
+
+    Ok(Some(serde_json::from_slice(&contents)?))
+}
+
+/// Serializes a value as pretty JSON and writes it atomically.
+pub fn save_json<T: serde::Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let contents = serde_json::to_vec_pretty(value)?;
+
+    write_atomic(path, &contents)
+}
+
+/// Writes to a temporary file and renames it over the target, so a crash mid-write
+/// leaves either the old or the new file in place but never a partial one.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+
+    {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
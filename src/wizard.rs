@@ -0,0 +1,530 @@
+use crate::{
+    catalog::{TraitOption, MAX_OPTIONS},
+    Context, Error,
+};
+use poise::serenity_prelude as serenity;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+const SELECT_ID: &str = "wizard_select";
+const JUMP_ID: &str = "wizard_jump";
+const BACK_ID: &str = "wizard_back";
+const NEXT_ID: &str = "wizard_next";
+const SUBMIT_ID: &str = "wizard_submit";
+const CANCEL_ID: &str = "wizard_cancel";
+const EXPAND_ID: &str = "wizard_expand";
+const OTHER_VALUE: &str = "wizard_other";
+
+/// Prefix of the values typed in through a step's "Other…" option.
+pub const CUSTOM_PREFIX: &str = "custom:";
+
+/// Returns whether an option value would be mistaken for one the wizard adds itself.
+pub fn is_reserved(value: &str) -> bool {
+    value == OTHER_VALUE || value.starts_with(CUSTOM_PREFIX)
+}
+
+/// A single page of a wizard, asking the user to pick from a select menu.
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub id: String,
+    pub name: String,
+    pub prompt: String,
+    pub placeholder: String,
+    pub options: Vec<TraitOption>,
+    pub min_values: u8,
+    pub max_values: u8,
+    pub required: bool,
+    /// A higher maximum the user can switch on, along with the label of its toggle.
+    pub expand: Option<(u8, String)>,
+    /// Whether the user can type in their own value through an "Other…" option.
+    pub other: bool,
+}
+
+impl Step {
+    /// Creates a required single-choice step.
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        prompt: impl Into<String>,
+        placeholder: impl Into<String>,
+        options: Vec<TraitOption>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            prompt: prompt.into(),
+            placeholder: placeholder.into(),
+            options,
+            min_values: 1,
+            max_values: 1,
+            required: true,
+            expand: None,
+            other: false,
+        }
+    }
+
+    /// Sets how many options may be picked at once.
+    pub fn values(mut self, min: u8, max: u8) -> Self {
+        self.min_values = min;
+        self.max_values = max;
+        self
+    }
+
+    /// Sets whether the step needs a selection before the wizard can be submitted. Steps can
+    /// still be skipped while moving between them, and are flagged on the review page.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Adds a toggle that raises how many options may be picked, e.g. for hybrid species.
+    pub fn expandable(mut self, max: u8, label: impl Into<String>) -> Self {
+        self.expand = Some((max, label.into()));
+        self
+    }
+
+    /// Adds an "Other…" option that asks the user to type in their own value with a modal. The
+    /// typed value is selected with [`CUSTOM_PREFIX`] in front of it.
+    pub fn other(mut self, other: bool) -> Self {
+        self.other = other;
+        self
+    }
+
+    /// Returns the labels of the selected values, for display.
+    fn labels(&self, values: &[String]) -> Vec<String> {
+        values
+            .iter()
+            .map(|v| {
+                if let Some(custom) = v.strip_prefix(CUSTOM_PREFIX) {
+                    return format!("{custom} (custom)");
+                }
+
+                self.options
+                    .iter()
+                    .find(|o| &o.value == v)
+                    .map_or_else(|| v.clone(), |o| o.label.clone())
+            })
+            .collect()
+    }
+
+    /// Whether the "Other…" option fits in the select menu.
+    fn offers_other(&self) -> bool {
+        self.other && self.options.len() < MAX_OPTIONS
+    }
+}
+
+/// The values picked in each step of a wizard, keyed by step id.
+#[derive(Debug, Clone, Default)]
+pub struct Selections(HashMap<String, Vec<String>>);
+
+impl Selections {
+    /// Returns every value picked in a step.
+    pub fn many(&self, id: &str) -> Vec<String> {
+        self.0.get(id).cloned().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, id: impl Into<String>, values: Vec<String>) {
+        self.0.insert(id.into(), values);
+    }
+
+    fn is_empty(&self, id: &str) -> bool {
+        self.0.get(id).is_none_or(|v| v.is_empty())
+    }
+}
+
+/// How a wizard ended.
+pub enum Outcome {
+    Completed(Selections),
+    Cancelled,
+    TimedOut,
+}
+
+/// Checks the selections when submitting, returning why they can't be submitted.
+type Validator = Box<dyn Fn(&Selections) -> Option<String> + Send + Sync>;
+
+/// A multi-step form made of select menus with back, next and cancel buttons, ending in a
+/// review page where every selection can be checked before submitting.
+pub struct Wizard {
+    steps: Vec<Step>,
+    selections: Selections,
+    submit_label: String,
+    review: bool,
+    validator: Option<Validator>,
+    /// Ids of the steps whose expanded maximum is switched on.
+    expanded: HashSet<String>,
+    timeout: Duration,
+}
+
+impl Wizard {
+    pub fn new(steps: Vec<Step>) -> Self {
+        Self {
+            steps,
+            selections: Selections::default(),
+            submit_label: "Submit".to_string(),
+            review: true,
+            validator: None,
+            expanded: HashSet::new(),
+            timeout: Duration::from_secs(300),
+        }
+    }
+
+    /// Pre-fills the wizard, e.g. with the current values when editing.
+    pub fn selections(mut self, selections: Selections) -> Self {
+        self.selections = selections;
+        self
+    }
+
+    /// Submits straight from the last step instead of showing the review page.
+    pub fn without_review(mut self) -> Self {
+        self.review = false;
+        self
+    }
+
+    /// Checks the selections before submitting, keeping the wizard open with the returned
+    /// explanation if they are rejected.
+    pub fn validate(
+        mut self,
+        validator: impl Fn(&Selections) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.validator = Some(Box::new(validator));
+        self
+    }
+
+    /// Sets the label of the button that confirms the review page.
+    pub fn submit_label(mut self, label: impl Into<String>) -> Self {
+        self.submit_label = label.into();
+        self
+    }
+
+    /// Runs the wizard in the given message until the user submits, cancels, or it times out.
+    pub async fn run(
+        mut self,
+        ctx: Context<'_>,
+        msg: &poise::ReplyHandle<'_>,
+    ) -> Result<Outcome, Error> {
+        let mut page = 0;
+
+        // Pre-filled selections may already need the expanded maximum
+        for step in &self.steps {
+            if self.selections.many(&step.id).len() > step.max_values as usize {
+                self.expanded.insert(step.id.clone());
+            }
+        }
+
+        msg.edit(ctx, self.render(page, None)).await?;
+
+        let message_id = msg.message().await?.id;
+
+        while let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+            .author_id(ctx.author().id)
+            .message_id(message_id)
+            .timeout(self.timeout)
+            .await
+        {
+            let mut warning = None;
+            let mut responded = false;
+
+            // Step components carry the id of their step, as a click on an old version of the
+            // message can arrive after the page has changed
+            let custom_id = interaction.data.custom_id.as_str();
+            let (action, step_id) = custom_id.split_once(':').unwrap_or((custom_id, ""));
+            let step = self.steps.iter().find(|s| s.id == step_id);
+
+            match action {
+                SELECT_ID => {
+                    if let (
+                        Some(step),
+                        serenity::ComponentInteractionDataKind::StringSelect { values },
+                    ) = (step, &interaction.data.kind)
+                    {
+                        let mut values = values.clone();
+
+                        if let Some(index) = values.iter().position(|v| v == OTHER_VALUE) {
+                            // The modal is the response to the select menu
+                            responded = true;
+
+                            match ask_other(ctx, &interaction, &step.name).await? {
+                                Some(custom) => values[index] = format!("{CUSTOM_PREFIX}{custom}"),
+                                None => values = self.selections.many(&step.id),
+                            }
+                        }
+
+                        self.selections.set(&step.id, values);
+                    }
+                }
+                JUMP_ID => {
+                    if let serenity::ComponentInteractionDataKind::StringSelect { values } =
+                        &interaction.data.kind
+                    {
+                        if let Some(index) = values
+                            .first()
+                            .and_then(|id| self.steps.iter().position(|s| &s.id == id))
+                        {
+                            page = index;
+                        }
+                    }
+                }
+                EXPAND_ID => {
+                    if let Some(step) = step {
+                        if !self.expanded.remove(&step.id) {
+                            self.expanded.insert(step.id.clone());
+                        } else {
+                            // Drop the picks that no longer fit
+                            let mut selected = self.selections.many(&step.id);
+                            selected.truncate(step.max_values as usize);
+
+                            self.selections.set(&step.id, selected);
+                        }
+                    }
+                }
+                BACK_ID => {
+                    page = page.saturating_sub(1);
+                }
+                NEXT_ID => {
+                    page = (page + 1).min(self.steps.len());
+                }
+                SUBMIT_ID => {
+                    if !self.missing().is_empty() {
+                        warning = Some("Some required steps still need a selection.".to_string());
+                    } else if let Some(problem) = self.problem() {
+                        warning = Some(problem);
+                    } else {
+                        interaction.defer(ctx).await?;
+
+                        return Ok(Outcome::Completed(self.selections));
+                    }
+                }
+                CANCEL_ID => {
+                    interaction.defer(ctx).await?;
+
+                    return Ok(Outcome::Cancelled);
+                }
+                _ => {}
+            }
+
+            msg.edit(ctx, self.render(page, warning.as_deref())).await?;
+
+            if !responded {
+                interaction.defer(ctx).await?;
+            }
+        }
+
+        Ok(Outcome::TimedOut)
+    }
+
+    /// Returns the required steps that don't have a selection yet.
+    fn missing(&self) -> Vec<&Step> {
+        self.steps
+            .iter()
+            .filter(|s| s.required && self.selections.is_empty(&s.id))
+            .collect()
+    }
+
+    fn problem(&self) -> Option<String> {
+        self.validator.as_ref()?(&self.selections)
+    }
+
+    fn render(&self, page: usize, warning: Option<&str>) -> poise::CreateReply {
+        if page == self.steps.len() {
+            return self.render_review(warning);
+        }
+
+        let step = &self.steps[page];
+        let selected = self.selections.many(&step.id);
+
+        let mut content = format!(
+            "**Step {} of {}**\n{}",
+            page + 1,
+            self.steps.len(),
+            step.prompt
+        );
+
+        if !selected.is_empty() {
+            content.push_str(&format!(" Selected: {}", step.labels(&selected).join(", ")));
+        }
+
+        if let Some(warning) = warning {
+            content.push_str(&format!("\n:warning: {warning}"));
+        }
+
+        let mut options = step
+            .options
+            .iter()
+            .map(|o| {
+                o.menu_option()
+                    .default_selection(selected.contains(&o.value))
+            })
+            .collect::<Vec<_>>();
+
+        if step.offers_other() {
+            let custom = selected.iter().find_map(|v| v.strip_prefix(CUSTOM_PREFIX));
+
+            options.push(
+                serenity::CreateSelectMenuOption::new("Other…", OTHER_VALUE)
+                    .description(match custom {
+                        Some(custom) => format!("Typed in: {custom}"),
+                        None => "Type in your own".to_string(),
+                    })
+                    .default_selection(custom.is_some()),
+            );
+        }
+
+        let option_count = options.len() as u8;
+
+        let menu = serenity::CreateSelectMenu::new(
+            format!("{SELECT_ID}:{}", step.id),
+            serenity::CreateSelectMenuKind::String { options },
+        )
+        .placeholder(&step.placeholder)
+        .min_values(step.min_values.min(option_count))
+        .max_values(self.max_values(step).min(option_count));
+
+        let forward = match page == self.steps.len() - 1 {
+            true if self.review => serenity::CreateButton::new(NEXT_ID).label("Review"),
+            true => serenity::CreateButton::new(SUBMIT_ID).label(&self.submit_label),
+            false => serenity::CreateButton::new(NEXT_ID).label("Next"),
+        };
+
+        let mut buttons = vec![
+            serenity::CreateButton::new(BACK_ID)
+                .label("Go Back")
+                .style(serenity::ButtonStyle::Primary)
+                .disabled(page == 0),
+            forward.style(serenity::ButtonStyle::Success),
+        ];
+
+        if let Some((_, label)) = &step.expand {
+            let style = if self.expanded.contains(&step.id) {
+                serenity::ButtonStyle::Primary
+            } else {
+                serenity::ButtonStyle::Secondary
+            };
+
+            buttons.push(
+                serenity::CreateButton::new(format!("{EXPAND_ID}:{}", step.id))
+                    .label(label)
+                    .style(style),
+            );
+        }
+
+        buttons.push(
+            serenity::CreateButton::new(CANCEL_ID)
+                .label("Cancel")
+                .style(serenity::ButtonStyle::Danger),
+        );
+
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(content)
+            .components(vec![
+                serenity::CreateActionRow::SelectMenu(menu),
+                serenity::CreateActionRow::Buttons(buttons),
+            ])
+    }
+
+    /// Returns how many options may be picked in a step, raised if its toggle is switched on.
+    fn max_values(&self, step: &Step) -> u8 {
+        match &step.expand {
+            Some((max, _)) if self.expanded.contains(&step.id) => *max,
+            _ => step.max_values,
+        }
+    }
+
+    fn render_review(&self, warning: Option<&str>) -> poise::CreateReply {
+        let missing = self.missing();
+
+        let mut content = String::from("**Review**\n");
+
+        for step in &self.steps {
+            let selected = self.selections.many(&step.id);
+
+            let value = if !selected.is_empty() {
+                step.labels(&selected).join(", ")
+            } else if step.required {
+                ":x: *missing*".to_string()
+            } else {
+                "*none*".to_string()
+            };
+
+            content.push_str(&format!("**{}:** {value}\n", step.name));
+        }
+
+        if !missing.is_empty() {
+            let names = missing
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            content.push_str(&format!("\n:warning: Still required: {names}"));
+        } else if let Some(warning) = warning {
+            content.push_str(&format!("\n:warning: {warning}"));
+        }
+
+        let options = self
+            .steps
+            .iter()
+            .map(|s| serenity::CreateSelectMenuOption::new(&s.name, &s.id))
+            .collect();
+
+        let menu = serenity::CreateSelectMenu::new(
+            JUMP_ID,
+            serenity::CreateSelectMenuKind::String { options },
+        )
+        .placeholder("Change a step");
+
+        poise::CreateReply::default()
+            .ephemeral(true)
+            .content(content)
+            .components(vec![
+                serenity::CreateActionRow::SelectMenu(menu),
+                serenity::CreateActionRow::Buttons(vec![
+                    serenity::CreateButton::new(BACK_ID)
+                        .label("Go Back")
+                        .style(serenity::ButtonStyle::Primary),
+                    serenity::CreateButton::new(SUBMIT_ID)
+                        .label(&self.submit_label)
+                        .style(serenity::ButtonStyle::Success)
+                        .disabled(!missing.is_empty()),
+                    serenity::CreateButton::new(CANCEL_ID)
+                        .label("Cancel")
+                        .style(serenity::ButtonStyle::Danger),
+                ]),
+            ])
+    }
+}
+
+/// Asks the user to type in their own value for a step, returning `None` if they close the modal,
+/// leave it empty, or type something that would become a value the wizard reserves.
+async fn ask_other(
+    ctx: Context<'_>,
+    interaction: &serenity::ComponentInteraction,
+    name: &str,
+) -> Result<Option<String>, Error> {
+    let input = serenity::CreateInputText::new(serenity::InputTextStyle::Short, name, "other")
+        .max_length(32)
+        .required(true);
+
+    let modal = serenity::CreateQuickModal::new(format!("Other {}", name.to_lowercase()))
+        .timeout(Duration::from_secs(300))
+        .field(input);
+
+    let Some(response) = interaction
+        .quick_modal(ctx.serenity_context(), modal)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    response
+        .interaction
+        .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+        .await?;
+
+    let value = response.inputs.into_iter().next().unwrap_or_default();
+
+    Ok(Some(value.trim().to_string())
+        .filter(|v| !v.is_empty() && !is_reserved(&TraitOption::value_for(v))))
+}